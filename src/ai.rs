@@ -0,0 +1,302 @@
+//Negamax search with alpha-beta pruning that the bots and the engine protocol play with
+use std::time::{Duration, Instant};
+
+use crate::{Cell, Coordinate, GameState, Move, Size, PADDING};
+
+//Scores at or above WIN_THRESHOLD are forced wins, WIN_SCORE minus the number of plies to get there
+pub const WIN_SCORE: i32 = 1_000_000;
+pub const WIN_THRESHOLD: i32 = WIN_SCORE - 1_000;
+const MAX_DEPTH: u32 = 64;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Limit {
+    Depth(u32),
+    MoveTime(Duration),
+}
+
+//Result of one finished iteration of the search, from the side to move's point of view
+#[derive(Debug, Clone)]
+pub struct SearchInfo {
+    pub depth: u32,
+    pub score: i32,
+    pub nodes: u64,
+    pub elapsed: Duration,
+    pub pv: Vec<Move>,
+}
+
+struct Searcher {
+    nodes: u64,
+    deadline: Option<Instant>,
+}
+
+//Negamax takes every other player's gain as the mover's loss, which only holds in a two player
+//game. With more players the scores would be meaningless, so nothing here searches those games
+pub fn supports(game_state: &GameState) -> bool {
+    game_state.turn_order.len() == 2
+}
+
+//Searches deeper and deeper until the limit runs out, calling report after every finished depth.
+//Returns None when the side to move has no legal moves or the game is not one the search supports
+pub fn search(
+    game_state: &GameState,
+    limit: Limit,
+    mut report: impl FnMut(&SearchInfo),
+) -> Option<SearchInfo> {
+    if !supports(game_state) {
+        return None;
+    }
+    let started = Instant::now();
+    let (max_depth, deadline) = match limit {
+        Limit::Depth(depth) => (depth.max(1), None),
+        Limit::MoveTime(time) => (MAX_DEPTH, Some(started + time)),
+    };
+    let mut searcher = Searcher { nodes: 0, deadline };
+    let mut best: Option<SearchInfo> = None;
+
+    for depth in 1..=max_depth {
+        let hint = best.as_ref().and_then(|info| info.pv.first().cloned());
+        //the first iteration always finishes so there is a move to play
+        searcher.deadline = if depth > 1 { deadline } else { None };
+        let Some((score, pv)) = searcher.negamax(game_state, depth, -WIN_SCORE, WIN_SCORE, 0, hint)
+        else {
+            break;
+        };
+        if pv.is_empty() {
+            break;
+        }
+        let info = SearchInfo {
+            depth,
+            score,
+            nodes: searcher.nodes,
+            elapsed: started.elapsed(),
+            pv,
+        };
+        report(&info);
+        best = Some(info);
+        if score.abs() >= WIN_THRESHOLD {
+            break;
+        }
+    }
+    best
+}
+
+//Searches every legal move depth plies deep, counting the move itself, and returns them best
+//first. Unlike search each move gets its exact score so they can be compared, which costs a lot
+//more than finding only the best one
+pub fn analyze(game_state: &GameState, depth: u32) -> Vec<SearchInfo> {
+    let mut analysis: Vec<SearchInfo> = game_state
+        .legal_moves()
+        .iter()
+        .filter_map(|player_move| analyze_move(game_state, player_move, depth))
+        .collect();
+    analysis.sort_by_key(|info| -info.score);
+    analysis
+}
+
+//Exact score of one move searched depth plies deep, counting the move, with the moves expected to
+//follow it. None when the move is illegal or the game is not one the search supports
+pub fn analyze_move(game_state: &GameState, player_move: &Move, depth: u32) -> Option<SearchInfo> {
+    if !supports(game_state) {
+        return None;
+    }
+    let started = Instant::now();
+    let depth = depth.max(1);
+    let player = game_state.to_move();
+    let mut searcher = Searcher {
+        nodes: 0,
+        deadline: None,
+    };
+    let mut child = game_state.clone();
+    child.play(player_move).ok()?;
+    //without a deadline the search always finishes
+    let (score, mut pv) = match child.winner {
+        Some(winner) => (terminal_score(winner, player, 1), Vec::new()),
+        None if child.to_move() == player => {
+            searcher.negamax(&child, depth - 1, -WIN_SCORE, WIN_SCORE, 2, None)?
+        }
+        None => {
+            let (score, pv) =
+                searcher.negamax(&child, depth - 1, -WIN_SCORE, WIN_SCORE, 1, None)?;
+            (-score, pv)
+        }
+    };
+    pv.insert(0, player_move.clone());
+    Some(SearchInfo {
+        depth,
+        score,
+        nodes: searcher.nodes,
+        elapsed: started.elapsed(),
+        pv,
+    })
+}
+
+//The number of their own moves the player needs to win, negative when the other side wins, for
+//scores that are forced wins
+pub fn mate_in(score: i32) -> Option<i32> {
+    if score.abs() < WIN_THRESHOLD {
+        return None;
+    }
+    //plies to the win, turned into the number of our own moves like UCI's "mate"
+    let moves = (WIN_SCORE - score.abs() + 1) / 2;
+    Some(if score > 0 { moves } else { -moves })
+}
+
+//Score of a finished game for the player to move in game_state, found ply plies into the search
+fn terminal_score(winner: usize, player: usize, ply: u32) -> i32 {
+    if winner == player {
+        WIN_SCORE - ply as i32
+    } else {
+        -(WIN_SCORE - ply as i32)
+    }
+}
+
+impl Searcher {
+    fn negamax(
+        &mut self,
+        game_state: &GameState,
+        depth: u32,
+        mut alpha: i32,
+        beta: i32,
+        ply: u32,
+        hint: Option<Move>,
+    ) -> Option<(i32, Vec<Move>)> {
+        self.nodes += 1;
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return None;
+        }
+        if depth == 0 {
+            return Some((evaluate(game_state), Vec::new()));
+        }
+
+        //play passes for players who cannot move, so only a game nobody can move in is left
+        //without moves, and that is a draw
+        let mut moves = game_state.legal_moves();
+        if moves.is_empty() {
+            return Some((0, Vec::new()));
+        }
+        if let Some(hint) = hint {
+            if let Some(index) = moves.iter().position(|m| *m == hint) {
+                moves.swap(0, index);
+            }
+        }
+
+        let player = game_state.to_move();
+        let mut best_score = -WIN_SCORE - 1;
+        let mut best_pv = Vec::new();
+        for player_move in moves {
+            let mut child = game_state.clone();
+            if child.play(&player_move).is_err() {
+                continue;
+            }
+            let (score, mut pv) = match child.winner {
+                Some(winner) => (terminal_score(winner, player, ply + 1), Vec::new()),
+                //the opponent had nothing to place and passed, so the score stays the mover's.
+                //The pass counts as a ply so mate distances stay in the mover's own moves
+                None if child.to_move() == player => {
+                    self.negamax(&child, depth - 1, alpha, beta, ply + 2, None)?
+                }
+                None => {
+                    let (score, pv) =
+                        self.negamax(&child, depth - 1, -beta, -alpha, ply + 1, None)?;
+                    (-score, pv)
+                }
+            };
+            if score > best_score {
+                best_score = score;
+                pv.insert(0, player_move);
+                best_pv = pv;
+            }
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+        Some((best_score, best_pv))
+    }
+}
+
+//Static evaluation from the point of view of the player to move
+pub fn evaluate(game_state: &GameState) -> i32 {
+    let mut scores = vec![0; game_state.turn_order.len()];
+
+    for (owner, player) in game_state.turn_order.iter().enumerate() {
+        let (_, cats) = player.pool_counts();
+        scores[owner] += cats as i32 * 100;
+    }
+
+    let rules = &game_state.rules;
+    for Coordinate { x, y } in rules.board_cells() {
+        let Cell::Piece(piece) = &game_state.game_board[x][y] else {
+            continue;
+        };
+        let centrality = (x - PADDING).min(PADDING + rules.width - 1 - x)
+            + (y - PADDING).min(PADDING + rules.height - 1 - y);
+        scores[piece.owner] += match piece.size {
+            Size::Small => 10,
+            Size::Big => 115,
+        };
+        scores[piece.owner] += centrality as i32 * 3;
+
+        //pairs of pieces next to each other threaten to make a line
+        for (dx, dy) in [(1, 0), (0, 1), (1, 1), (1, -1)] {
+            let neighbour = rules.offset(Coordinate { x, y }, dx, dy).unwrap();
+            if let Cell::Piece(neighbour) = &game_state.game_board[neighbour.x][neighbour.y] {
+                if neighbour.owner == piece.owner {
+                    scores[piece.owner] += match (piece.size, neighbour.size) {
+                        (Size::Big, Size::Big) => 40,
+                        _ => 15,
+                    };
+                }
+            }
+        }
+    }
+
+    let player = game_state.to_move();
+    let opponents: i32 = scores
+        .iter()
+        .enumerate()
+        .filter(|(owner, _)| *owner != player)
+        .map(|(_, score)| score)
+        .sum();
+    scores[player] - opponents
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RuleSet;
+
+    #[test]
+    fn finds_the_winning_move() {
+        let game_state: GameState = "....../.OO.../....../....../....../...... o xxxxxxxx Oooooo"
+            .parse()
+            .unwrap();
+        let info = search(&game_state, Limit::Depth(2), |_| ()).unwrap();
+        assert_eq!(mate_in(info.score), Some(1));
+    }
+
+    #[test]
+    fn plays_on_while_the_opponent_passes() {
+        let rules = RuleSet {
+            booped_off_to_pool: false,
+            ..RuleSet::default()
+        };
+        //Player 1 has nothing to place, Player 2 needs two cats in a row next to the one boxed in
+        //at the edge
+        let game_state =
+            GameState::from_notation("x...../x...../O...../....../....../...... o - OOooo", rules)
+                .unwrap();
+        let info = search(&game_state, Limit::Depth(3), |_| ()).unwrap();
+        assert_eq!(mate_in(info.score), Some(2));
+    }
+
+    #[test]
+    fn refuses_games_with_more_than_two_players() {
+        let game_state = GameState::with_rules(RuleSet::for_players(3));
+        assert!(search(&game_state, Limit::Depth(1), |_| ()).is_none());
+        assert!(analyze(&game_state, 1).is_empty());
+    }
+}
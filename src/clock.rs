@@ -0,0 +1,289 @@
+//Chess style game clocks. Each player has a time bank that only runs on their own turn, with an
+//optional increment added after every move or delay that has to pass before the bank starts
+//counting down. Time comes from a TimeSource so tests can drive the clock by hand
+use std::{
+    error::Error,
+    fmt,
+    num::ParseIntError,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+pub trait TimeSource {
+    //Time passed since some fixed point, only ever compared with earlier readings
+    fn now(&self) -> Duration;
+}
+
+pub struct SystemTime {
+    started: Instant,
+}
+impl SystemTime {
+    pub fn new() -> SystemTime {
+        SystemTime {
+            started: Instant::now(),
+        }
+    }
+}
+impl TimeSource for SystemTime {
+    fn now(&self) -> Duration {
+        self.started.elapsed()
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseTimeControlError {
+    kind: TimeControlErrorKind,
+}
+#[derive(Debug)]
+enum TimeControlErrorKind {
+    Empty,
+    InvalidFormat,
+    ValueError(ParseIntError),
+}
+impl ParseTimeControlError {
+    pub fn __description(&self) -> &str {
+        match self.kind {
+            TimeControlErrorKind::Empty => "cannot parse time control from empty string",
+            TimeControlErrorKind::InvalidFormat => {
+                "time control should be minutes with an optional increment or delay in seconds, like \"5\", \"5+3\" or \"5d3\""
+            }
+            TimeControlErrorKind::ValueError(_) => "invalid number in time control",
+        }
+    }
+}
+impl fmt::Display for ParseTimeControlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.__description().fmt(f)
+    }
+}
+impl Error for ParseTimeControlError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            TimeControlErrorKind::ValueError(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeControl {
+    pub initial: Duration,
+    pub increment: Duration,
+    pub delay: Duration,
+}
+//"5" is five minutes each, "5+3" adds three seconds after every move and "5d3" gives three
+//seconds of delay every move
+impl FromStr for TimeControl {
+    type Err = ParseTimeControlError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Result::Err(ParseTimeControlError {
+                kind: TimeControlErrorKind::Empty,
+            });
+        }
+        let parse = |value: &str| {
+            value
+                .trim()
+                .parse::<u64>()
+                .map_err(|error| ParseTimeControlError {
+                    kind: TimeControlErrorKind::ValueError(error),
+                })
+        };
+
+        let (minutes, extra) = match s.find(['+', 'd']) {
+            Some(index) => (&s[..index], Some((&s[index..index + 1], &s[index + 1..]))),
+            None => (s, None),
+        };
+        let initial = Duration::from_secs(parse(minutes)? * 60);
+        let mut time_control = TimeControl {
+            initial,
+            increment: Duration::ZERO,
+            delay: Duration::ZERO,
+        };
+        match extra {
+            None => (),
+            Some(("+", seconds)) => time_control.increment = Duration::from_secs(parse(seconds)?),
+            Some(("d", seconds)) => time_control.delay = Duration::from_secs(parse(seconds)?),
+            Some(_) => {
+                return Result::Err(ParseTimeControlError {
+                    kind: TimeControlErrorKind::InvalidFormat,
+                })
+            }
+        }
+        if initial.is_zero() {
+            return Result::Err(ParseTimeControlError {
+                kind: TimeControlErrorKind::InvalidFormat,
+            });
+        }
+        Result::Ok(time_control)
+    }
+}
+impl fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.initial.as_secs() / 60)?;
+        if !self.increment.is_zero() {
+            write!(f, "+{}", self.increment.as_secs())?;
+        }
+        if !self.delay.is_zero() {
+            write!(f, "d{}", self.delay.as_secs())?;
+        }
+        Result::Ok(())
+    }
+}
+
+pub struct Clock<T: TimeSource> {
+    time_control: TimeControl,
+    remaining: Vec<Duration>,
+    //player whose time is running and when their turn started
+    running: Option<(usize, Duration)>,
+    source: T,
+}
+impl<T: TimeSource> Clock<T> {
+    pub fn new(time_control: TimeControl, players: usize, source: T) -> Clock<T> {
+        Clock {
+            time_control,
+            remaining: vec![time_control.initial; players],
+            running: None,
+            source,
+        }
+    }
+
+    //Starts the player's turn, does nothing when their time is already running
+    pub fn start(&mut self, player: usize) {
+        if self.running.is_none() {
+            self.running = Some((player, self.source.now()));
+        }
+    }
+
+    //Time the player had spent on this turn that comes off their bank
+    fn charged(&self, player: usize) -> Duration {
+        match self.running {
+            Some((running, started)) if running == player => self
+                .source
+                .now()
+                .saturating_sub(started)
+                .saturating_sub(self.time_control.delay),
+            _ => Duration::ZERO,
+        }
+    }
+
+    pub fn remaining(&self, player: usize) -> Duration {
+        self.remaining[player].saturating_sub(self.charged(player))
+    }
+
+    pub fn out_of_time(&self, player: usize) -> bool {
+        self.remaining(player).is_zero()
+    }
+
+    //Ends the running turn, adding the increment. Returns the player who ran out of time instead
+    //when the turn took longer than their bank
+    pub fn stop(&mut self) -> Option<usize> {
+        let (player, _) = self.running?;
+        let out_of_time = self.out_of_time(player);
+        self.remaining[player] = if out_of_time {
+            Duration::ZERO
+        } else {
+            self.remaining(player) + self.time_control.increment
+        };
+        self.running = None;
+        out_of_time.then_some(player)
+    }
+}
+
+//Remaining time as "m:ss", with tenths of a second once under ten seconds
+pub fn format_time(time: Duration) -> String {
+    let seconds = time.as_secs();
+    if seconds < 10 {
+        format!("0:{:02}.{}", seconds, time.subsec_millis() / 100)
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{cell::Cell, rc::Rc};
+
+    //Time that only moves when a test advances it, shared between the test and the clock
+    #[derive(Clone, Default)]
+    struct ManualTime(Rc<Cell<Duration>>);
+    impl ManualTime {
+        fn advance(&self, seconds: u64) {
+            self.0.set(self.0.get() + Duration::from_secs(seconds));
+        }
+    }
+    impl TimeSource for ManualTime {
+        fn now(&self) -> Duration {
+            self.0.get()
+        }
+    }
+
+    fn clock(time_control: &str) -> (Clock<ManualTime>, ManualTime) {
+        let time = ManualTime::default();
+        let clock = Clock::new(time_control.parse().unwrap(), 2, time.clone());
+        (clock, time)
+    }
+
+    #[test]
+    fn only_runs_on_the_players_turn() {
+        let (mut clock, time) = clock("1");
+        clock.start(0);
+        time.advance(10);
+        assert_eq!(clock.remaining(0), Duration::from_secs(50));
+        assert_eq!(clock.remaining(1), Duration::from_secs(60));
+        assert_eq!(clock.stop(), None);
+        time.advance(100);
+        assert_eq!(clock.remaining(0), Duration::from_secs(50));
+    }
+
+    #[test]
+    fn adds_increment_after_the_move() {
+        let (mut clock, time) = clock("1+5");
+        clock.start(0);
+        time.advance(10);
+        assert_eq!(clock.remaining(0), Duration::from_secs(50));
+        assert_eq!(clock.stop(), None);
+        assert_eq!(clock.remaining(0), Duration::from_secs(55));
+    }
+
+    #[test]
+    fn delay_passes_before_the_bank_runs() {
+        let (mut clock, time) = clock("1d5");
+        clock.start(1);
+        time.advance(3);
+        assert_eq!(clock.remaining(1), Duration::from_secs(60));
+        time.advance(4);
+        assert_eq!(clock.remaining(1), Duration::from_secs(58));
+        assert_eq!(clock.stop(), None);
+        assert_eq!(clock.remaining(1), Duration::from_secs(58));
+    }
+
+    #[test]
+    fn flag_falls_when_the_bank_runs_out() {
+        let (mut clock, time) = clock("1+5");
+        clock.start(0);
+        time.advance(59);
+        assert!(!clock.out_of_time(0));
+        time.advance(1);
+        assert!(clock.out_of_time(0));
+        //the increment doesn't save a player whose flag has already fallen
+        assert_eq!(clock.stop(), Some(0));
+        assert_eq!(clock.remaining(0), Duration::ZERO);
+    }
+
+    #[test]
+    fn stop_without_a_running_turn_does_nothing() {
+        let (mut clock, time) = clock("1+5");
+        assert_eq!(clock.stop(), None);
+        clock.start(0);
+        //starting again mid-turn keeps the original start
+        time.advance(10);
+        clock.start(0);
+        assert_eq!(clock.stop(), None);
+        assert_eq!(clock.stop(), None);
+        assert_eq!(clock.remaining(0), Duration::from_secs(55));
+    }
+}
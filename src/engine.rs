@@ -0,0 +1,200 @@
+//Line-oriented text protocol on stdin/stdout so GUIs and bots in other languages can drive the
+//game, in the spirit of UCI. See the "Engine protocol" section of the README for the commands
+use std::{
+    io::{self, BufRead, Write},
+    time::Duration,
+};
+
+use crate::{
+    ai::{self, Limit, SearchInfo},
+    error_chain, GameState, Move, RuleSet,
+};
+
+const DEFAULT_DEPTH: u32 = 3;
+
+//Games are played under the rules picked on the command line, the standard game by default
+pub fn run(rules: RuleSet) -> io::Result<()> {
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    run_protocol(stdin.lock(), &mut stdout, rules)
+}
+
+fn run_protocol(input: impl BufRead, output: &mut impl Write, rules: RuleSet) -> io::Result<()> {
+    let mut game_state = GameState::with_rules(rules);
+
+    for line in input.lines() {
+        let line = line?;
+        let mut words = line.split_whitespace();
+        let Some(command) = words.next() else {
+            continue;
+        };
+        let arguments: Vec<&str> = words.collect();
+
+        match command {
+            "boop" => {
+                writeln!(output, "id name boop_game {}", env!("CARGO_PKG_VERSION"))?;
+                writeln!(output, "boopok")?;
+            }
+            "isready" => writeln!(output, "readyok")?,
+            "newgame" => game_state = GameState::with_rules(rules),
+            "position" => match parse_position(&arguments, rules) {
+                Ok(position) => game_state = position,
+                Err(message) => writeln!(output, "info string {}", message)?,
+            },
+            "go" => match parse_limit(&arguments) {
+                Ok(limit) => go(&game_state, limit, output)?,
+                Err(message) => writeln!(output, "info string {}", message)?,
+            },
+            "d" => writeln!(output, "info string position {}", game_state.notation())?,
+            "quit" => break,
+            _ => writeln!(output, "info string unknown command {}", command)?,
+        }
+        output.flush()?;
+    }
+    Ok(())
+}
+
+//"startpos" or a position in notation, optionally followed by "moves" and the moves played since
+fn parse_position(arguments: &[&str], rules: RuleSet) -> Result<GameState, String> {
+    let moves_index = arguments
+        .iter()
+        .position(|word| *word == "moves")
+        .unwrap_or(arguments.len());
+    let (position, moves) = arguments.split_at(moves_index);
+
+    let mut game_state = match position {
+        ["startpos"] => GameState::with_rules(rules),
+        _ => GameState::from_notation(&position.join(" "), rules)
+            .map_err(|error| format!("invalid position: {}", error_chain(&error)))?,
+    };
+
+    for word in moves.iter().skip(1) {
+        let player_move: Move = word
+            .parse()
+            .map_err(|error| format!("invalid move {}: {}", word, error_chain(&error)))?;
+        game_state
+            .play(&player_move)
+            .map_err(|error| format!("illegal move {}: {}", word, error))?;
+    }
+    Ok(game_state)
+}
+
+fn parse_limit(arguments: &[&str]) -> Result<Limit, String> {
+    match arguments {
+        [] => Ok(Limit::Depth(DEFAULT_DEPTH)),
+        ["depth", depth] => depth
+            .parse()
+            .map(Limit::Depth)
+            .map_err(|error| format!("invalid depth {}: {}", depth, error)),
+        ["movetime", milliseconds] => milliseconds
+            .parse()
+            .map(|milliseconds| Limit::MoveTime(Duration::from_millis(milliseconds)))
+            .map_err(|error| format!("invalid movetime {}: {}", milliseconds, error)),
+        _ => Err(String::from("go expects \"depth N\" or \"movetime ms\"")),
+    }
+}
+
+fn format_info(info: &SearchInfo) -> String {
+    let score = match ai::mate_in(info.score) {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", info.score),
+    };
+    let pv: Vec<String> = info.pv.iter().map(Move::to_string).collect();
+    format!(
+        "info depth {} score {} nodes {} time {} pv {}",
+        info.depth,
+        score,
+        info.nodes,
+        info.elapsed.as_millis(),
+        pv.join(" ")
+    )
+}
+
+fn go(game_state: &GameState, limit: Limit, output: &mut impl Write) -> io::Result<()> {
+    if !ai::supports(game_state) {
+        writeln!(output, "info string the bot only plays two player games")?;
+        return writeln!(output, "bestmove none");
+    }
+    let mut result = Ok(());
+    let best = ai::search(game_state, limit, |info| {
+        if result.is_ok() {
+            result = writeln!(output, "{}", format_info(info));
+        }
+    });
+    result?;
+
+    match best.and_then(|info| info.pv.first().cloned()) {
+        Some(best_move) => writeln!(output, "bestmove {}", best_move),
+        None => writeln!(output, "bestmove none"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replies(input: &str) -> Vec<String> {
+        let mut output = Vec::new();
+        run_protocol(input.as_bytes(), &mut output, RuleSet::default()).unwrap();
+        String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn answers_handshake() {
+        assert_eq!(
+            replies("boop\nisready\n"),
+            vec![
+                format!("id name boop_game {}", env!("CARGO_PKG_VERSION")),
+                String::from("boopok"),
+                String::from("readyok"),
+            ]
+        );
+    }
+
+    #[test]
+    fn plays_moves_after_startpos() {
+        let mut game_state = GameState::init();
+        for player_move in ["s,2,3", "s,2,2"] {
+            game_state.play(&player_move.parse().unwrap()).unwrap();
+        }
+        assert_eq!(
+            replies("position startpos moves s,2,3 s,2,2\nd\n"),
+            vec![format!("info string position {}", game_state.notation())]
+        );
+    }
+
+    #[test]
+    fn go_finds_the_winning_move() {
+        let position = "....../.OO.../....../....../....../...... o xxxxxxxx Oooooo";
+        let output = replies(&format!("position {}\ngo depth 2\n", position));
+        assert!(output[0].starts_with("info depth 1 score mate 1 "));
+        let best_move = output.last().unwrap().strip_prefix("bestmove ").unwrap();
+
+        let mut game_state: GameState = position.parse().unwrap();
+        game_state.play(&best_move.parse().unwrap()).unwrap();
+        assert_eq!(game_state.winner, Some(1));
+    }
+
+    #[test]
+    fn reports_invalid_commands() {
+        let output = replies(
+            "fly\nposition startpos moves s,2,3 s,2,3\nposition startpos moves s,x,3\ngo depth x\ngo deep\n",
+        );
+        assert_eq!(output[0], "info string unknown command fly");
+        assert_eq!(
+            output[1],
+            "info string illegal move s,2,3: attempting to place piece at occupied position"
+        );
+        assert!(output[2].starts_with("info string invalid move s,x,3: "));
+        assert!(output[3].starts_with("info string invalid depth x: "));
+        assert_eq!(
+            output[4],
+            "info string go expects \"depth N\" or \"movetime ms\""
+        );
+        assert_eq!(output.len(), 5);
+    }
+}
@@ -0,0 +1,167 @@
+//Finds puzzles in bot self-play. After a few random opening moves, so the games don't all repeat
+//each other, the bot plays both sides, and the first position in a game where the side to move
+//has exactly one move that forces a win within the most moves asked for becomes a puzzle
+use crate::{
+    ai::{self, Limit},
+    puzzle::{self, Puzzle},
+    Cell, Coordinate, GameState, Move, MoveEvent,
+};
+
+//Proving a win in 4 takes too long to do for every position of a game
+pub const MAX_MOVES: usize = 3;
+const OPENING_PLIES: usize = 6;
+const MAX_PLIES: usize = 80;
+const BOT_DEPTH: u32 = 2;
+
+//xorshift, good enough to pick opening moves
+struct Random(u64);
+impl Random {
+    fn new(seed: u64) -> Random {
+        Random(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % bound as u64) as usize
+    }
+}
+
+//Plays games of self-play from seed and calls found with every puzzle that takes at most
+//max_moves to win, at most one a game
+pub fn generate(games: usize, seed: u64, max_moves: usize, mut found: impl FnMut(Puzzle)) {
+    let mut random = Random::new(seed);
+    for game in 0..games {
+        let mut game_state = GameState::init();
+        for ply in 0..MAX_PLIES {
+            let moves = game_state.legal_moves();
+            if moves.is_empty() {
+                break;
+            }
+            if ply >= OPENING_PLIES {
+                let name = format!("Self-play {}.{}", seed, game + 1);
+                if let Some(puzzle) = find_puzzle(&game_state, name, max_moves) {
+                    found(puzzle);
+                    break;
+                }
+            }
+            let player_move = if ply < OPENING_PLIES {
+                moves[random.below(moves.len())].clone()
+            } else {
+                match ai::search(&game_state, Limit::Depth(BOT_DEPTH), |_| ()) {
+                    Some(info) => info.pv[0].clone(),
+                    None => break,
+                }
+            };
+            if game_state.play(&player_move).is_err() || game_state.winner.is_some() {
+                break;
+            }
+        }
+    }
+}
+
+//The position as a puzzle when its quickest forced win starts with exactly one move
+fn find_puzzle(game_state: &GameState, name: String, max_moves: usize) -> Option<Puzzle> {
+    for moves in 1..=max_moves {
+        match puzzle::winning_moves(game_state, moves).as_slice() {
+            [] => continue,
+            [solution] => {
+                return Some(Puzzle {
+                    name,
+                    position: game_state.notation(),
+                    moves,
+                    difficulty: Some(difficulty(game_state, solution, moves)),
+                    theme: Some(themes(game_state, solution).join(", ")),
+                })
+            }
+            _ => return None,
+        }
+    }
+    None
+}
+
+//Two for every move, less one when the bot looking a single move ahead would play the solution
+//anyway
+pub fn difficulty(game_state: &GameState, solution: &Move, moves: usize) -> usize {
+    let glance = ai::search(game_state, Limit::Depth(1), |_| ());
+    let obvious = glance.is_some_and(|info| info.pv.first() == Some(solution));
+    2 * moves - usize::from(obvious)
+}
+
+//What the first move of the solution does to the board
+pub fn themes(game_state: &GameState, solution: &Move) -> Vec<&'static str> {
+    let mover = game_state.to_move();
+    let mut after = game_state.clone();
+    let events = after.play(solution).unwrap_or_default();
+    let owned_by_mover = |coordinate: &Coordinate| {
+        let coordinate = coordinate.padded();
+        matches!(
+            &game_state.game_board[coordinate.x][coordinate.y],
+            Cell::Piece(piece) if piece.owner == mover
+        )
+    };
+
+    let mut themes = Vec::new();
+    let boops = events
+        .iter()
+        .filter(|event| {
+            matches!(
+                event,
+                MoveEvent::Booped { .. } | MoveEvent::BoopedOff { .. }
+            )
+        })
+        .count();
+    if boops >= 2 {
+        themes.push("double boop");
+    }
+    if events
+        .iter()
+        .any(|event| matches!(event, MoveEvent::BoopedOff { piece, .. } if piece.owner != mover))
+    {
+        themes.push("edge push-off");
+    }
+    let pushed_into_line = events.iter().any(|event| match event {
+        MoveEvent::Booped { from, to } => {
+            owned_by_mover(from)
+                && events
+                    .iter()
+                    .any(|event| matches!(event, MoveEvent::LineFormed(line) if line.contains(*to)))
+        }
+        _ => false,
+    });
+    if pushed_into_line {
+        themes.push("push into line");
+    }
+    let lines = events
+        .iter()
+        .any(|event| matches!(event, MoveEvent::LineFormed(_)));
+    if lines && after.winner.is_none() {
+        themes.push("graduation");
+    }
+    if themes.is_empty() {
+        themes.push(if after.winner.is_some() {
+            "cat line"
+        } else {
+            "quiet move"
+        });
+    }
+    themes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_puzzles_are_sound() {
+        let mut puzzles = Vec::new();
+        generate(2, 1, 2, |puzzle| puzzles.push(puzzle));
+        assert!(!puzzles.is_empty());
+        for puzzle in puzzles {
+            if let Err(error) = puzzle.check() {
+                panic!("{}: {}", puzzle.name, crate::error_chain(&error));
+            }
+        }
+    }
+}
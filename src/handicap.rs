@@ -0,0 +1,125 @@
+//Preset handicaps for a new game. Each one gives Player 2, who should be the weaker player, a
+//head start over Player 1
+use std::{error::Error, fmt, str::FromStr};
+
+use crate::{GameState, Size};
+
+//Index of the player the handicaps help
+const HANDICAPPED_PLAYER: usize = 1;
+
+#[derive(Debug)]
+pub struct ParseHandicapError {
+    kind: HandicapErrorKind,
+}
+#[derive(Debug)]
+enum HandicapErrorKind {
+    Empty,
+    UnknownValue,
+}
+impl ParseHandicapError {
+    pub fn __description(&self) -> &str {
+        match self.kind {
+            HandicapErrorKind::Empty => "cannot parse handicap from empty string",
+            HandicapErrorKind::UnknownValue => {
+                "unknown handicap, should be \"cat\", \"two-cats\" or \"first-move\""
+            }
+        }
+    }
+}
+impl fmt::Display for ParseHandicapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.__description().fmt(f)
+    }
+}
+impl Error for ParseHandicapError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Handicap {
+    //Player 2 starts with a cat in their pool in place of one of their kittens
+    Cat,
+    //Player 2 starts with two cats in place of two kittens
+    TwoCats,
+    //Player 2 moves first
+    FirstMove,
+}
+impl FromStr for Handicap {
+    type Err = ParseHandicapError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "" => Err(ParseHandicapError {
+                kind: HandicapErrorKind::Empty,
+            }),
+            "cat" => Ok(Handicap::Cat),
+            "two-cats" => Ok(Handicap::TwoCats),
+            "first-move" => Ok(Handicap::FirstMove),
+            _ => Err(ParseHandicapError {
+                kind: HandicapErrorKind::UnknownValue,
+            }),
+        }
+    }
+}
+impl Handicap {
+    //Sets the handicap up in a game that has not started yet
+    pub fn apply(self, game_state: &mut GameState) {
+        match self {
+            Handicap::Cat => promote_kittens(game_state, 1),
+            Handicap::TwoCats => promote_kittens(game_state, 2),
+            Handicap::FirstMove => game_state.turn_count = HANDICAPPED_PLAYER,
+        }
+    }
+}
+
+fn promote_kittens(game_state: &mut GameState, count: usize) {
+    for piece in game_state.turn_order[HANDICAPPED_PLAYER]
+        .piece_pool
+        .iter_mut()
+        .filter(|piece| piece.size == Size::Small)
+        .take(count)
+    {
+        piece.size = Size::Big;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handicapped(handicap: &str) -> GameState {
+        let mut game_state = GameState::init();
+        handicap.parse::<Handicap>().unwrap().apply(&mut game_state);
+        game_state
+    }
+
+    #[test]
+    fn cat_presets_promote_player_two_kittens() {
+        assert_eq!(
+            handicapped("cat").notation(),
+            "....../....../....../....../....../...... x xxxxxxxx Oooooooo"
+        );
+        assert_eq!(
+            handicapped("two-cats").notation(),
+            "....../....../....../....../....../...... x xxxxxxxx OOoooooo"
+        );
+    }
+
+    #[test]
+    fn first_move_preset_lets_player_two_start() {
+        assert_eq!(
+            handicapped("first-move").notation(),
+            "....../....../....../....../....../...... o xxxxxxxx oooooooo"
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_handicaps() {
+        assert!(matches!(
+            "".parse::<Handicap>().unwrap_err().kind,
+            HandicapErrorKind::Empty
+        ));
+        assert!(matches!(
+            "queen".parse::<Handicap>().unwrap_err().kind,
+            HandicapErrorKind::UnknownValue
+        ));
+    }
+}
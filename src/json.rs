@@ -0,0 +1,734 @@
+//Machine readable interface for "--format json": game states, events and errors are written as
+//one JSON object per line and moves are read back as JSON objects
+use std::{
+    fmt,
+    io::{self, BufRead, Write},
+};
+
+use crate::{
+    Cell, Coordinate, CoordinateErrorKind, GameState, Move, MoveEvent, ParseCoordinateError,
+    ParsePiecePlacementError, ParseSizeError, Piece, PieceErrorKind, PiecePlacement,
+    PiecePlacementErrorKind, PlacePieceError, RuleSet, Size, SizeErrorKind, PADDING,
+};
+
+//Why a line of input could not be read as JSON at all, before its contents are looked at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseJsonError {
+    kind: JsonErrorKind,
+}
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum JsonErrorKind {
+    Empty,
+    UnexpectedEnd,
+    UnexpectedCharacter,
+    TrailingCharacters,
+    TooDeep,
+}
+impl ParseJsonError {
+    fn __description(&self) -> &str {
+        match self.kind {
+            JsonErrorKind::Empty => "cannot parse json from empty string",
+            JsonErrorKind::UnexpectedEnd => "json ended unexpectedly",
+            JsonErrorKind::UnexpectedCharacter => "unexpected character in json",
+            JsonErrorKind::TrailingCharacters => "trailing characters after json value",
+            JsonErrorKind::TooDeep => "json nested too deeply",
+        }
+    }
+}
+impl fmt::Display for ParseJsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.__description().fmt(f)
+    }
+}
+impl std::error::Error for ParseJsonError {}
+
+//Numbers keep their source text so they can go through the same parsing as typed input
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+impl Json {
+    pub fn object(fields: Vec<(&str, Json)>) -> Json {
+        Json::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    pub fn string(value: impl Into<String>) -> Json {
+        Json::String(value.into())
+    }
+
+    pub fn number(value: usize) -> Json {
+        Json::Number(value.to_string())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields
+                .iter()
+                .find(|(field, _)| field == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+}
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(value) => write!(f, "{}", value),
+            Json::Number(value) => write!(f, "{}", value),
+            Json::String(value) => {
+                write!(f, "\"")?;
+                for c in value.chars() {
+                    match c {
+                        '"' => write!(f, "\\\"")?,
+                        '\\' => write!(f, "\\\\")?,
+                        '\n' => write!(f, "\\n")?,
+                        '\r' => write!(f, "\\r")?,
+                        '\t' => write!(f, "\\t")?,
+                        c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+                        c => write!(f, "{}", c)?,
+                    }
+                }
+                write!(f, "\"")
+            }
+            Json::Array(values) => {
+                write!(f, "[")?;
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(fields) => {
+                write!(f, "{{")?;
+                for (index, (key, value)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}:{}", Json::String(key.clone()), value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+impl std::str::FromStr for Json {
+    type Err = ParseJsonError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().is_empty() {
+            return Err(ParseJsonError {
+                kind: JsonErrorKind::Empty,
+            });
+        }
+        let mut parser = Parser {
+            chars: s.chars().collect(),
+            position: 0,
+            depth: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.position != parser.chars.len() {
+            return Err(ParseJsonError {
+                kind: JsonErrorKind::TrailingCharacters,
+            });
+        }
+        Ok(value)
+    }
+}
+
+//Arrays and objects are parsed by recursion, so how deeply values may nest is capped to keep a
+//request body of nothing but brackets from overflowing the stack
+const MAX_DEPTH: usize = 64;
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+    depth: usize,
+}
+impl Parser {
+    fn skip_whitespace(&mut self) {
+        while self
+            .chars
+            .get(self.position)
+            .is_some_and(|c| c.is_whitespace())
+        {
+            self.position += 1;
+        }
+    }
+
+    fn next(&mut self) -> Result<char, ParseJsonError> {
+        let c = self.chars.get(self.position).ok_or(ParseJsonError {
+            kind: JsonErrorKind::UnexpectedEnd,
+        })?;
+        self.position += 1;
+        Ok(*c)
+    }
+
+    fn peek(&mut self) -> Result<char, ParseJsonError> {
+        self.skip_whitespace();
+        self.chars
+            .get(self.position)
+            .copied()
+            .ok_or(ParseJsonError {
+                kind: JsonErrorKind::UnexpectedEnd,
+            })
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ParseJsonError> {
+        self.skip_whitespace();
+        match self.next()? {
+            c if c == expected => Ok(()),
+            _ => Err(ParseJsonError {
+                kind: JsonErrorKind::UnexpectedCharacter,
+            }),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str, value: Json) -> Result<Json, ParseJsonError> {
+        for expected in keyword.chars() {
+            if self.next()? != expected {
+                return Err(ParseJsonError {
+                    kind: JsonErrorKind::UnexpectedCharacter,
+                });
+            }
+        }
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Json, ParseJsonError> {
+        if self.depth == MAX_DEPTH {
+            return Err(ParseJsonError {
+                kind: JsonErrorKind::TooDeep,
+            });
+        }
+        self.depth += 1;
+        let value = self.parse_value();
+        self.depth -= 1;
+        value
+    }
+
+    //The value starting at the current position, only called through value so nesting is counted
+    fn parse_value(&mut self) -> Result<Json, ParseJsonError> {
+        match self.peek()? {
+            'n' => self.keyword("null", Json::Null),
+            't' => self.keyword("true", Json::Bool(true)),
+            'f' => self.keyword("false", Json::Bool(false)),
+            '"' => Ok(Json::String(self.string()?)),
+            '[' => {
+                self.expect('[')?;
+                let mut values = Vec::new();
+                if self.peek()? == ']' {
+                    self.position += 1;
+                    return Ok(Json::Array(values));
+                }
+                loop {
+                    values.push(self.value()?);
+                    match self.peek()? {
+                        ',' => self.position += 1,
+                        ']' => {
+                            self.position += 1;
+                            return Ok(Json::Array(values));
+                        }
+                        _ => {
+                            return Err(ParseJsonError {
+                                kind: JsonErrorKind::UnexpectedCharacter,
+                            })
+                        }
+                    }
+                }
+            }
+            '{' => {
+                self.expect('{')?;
+                let mut fields = Vec::new();
+                if self.peek()? == '}' {
+                    self.position += 1;
+                    return Ok(Json::Object(fields));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let key = self.string()?;
+                    self.expect(':')?;
+                    fields.push((key, self.value()?));
+                    match self.peek()? {
+                        ',' => self.position += 1,
+                        '}' => {
+                            self.position += 1;
+                            return Ok(Json::Object(fields));
+                        }
+                        _ => {
+                            return Err(ParseJsonError {
+                                kind: JsonErrorKind::UnexpectedCharacter,
+                            })
+                        }
+                    }
+                }
+            }
+            c if c == '-' || c.is_ascii_digit() => {
+                let start = self.position;
+                while self
+                    .chars
+                    .get(self.position)
+                    .is_some_and(|c| c.is_ascii_digit() || "+-.eE".contains(*c))
+                {
+                    self.position += 1;
+                }
+                Ok(Json::Number(
+                    self.chars[start..self.position].iter().collect(),
+                ))
+            }
+            _ => Err(ParseJsonError {
+                kind: JsonErrorKind::UnexpectedCharacter,
+            }),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, ParseJsonError> {
+        self.expect('"')?;
+        let mut value = String::new();
+        loop {
+            match self.next()? {
+                '"' => return Ok(value),
+                '\\' => match self.next()? {
+                    'n' => value.push('\n'),
+                    'r' => value.push('\r'),
+                    't' => value.push('\t'),
+                    'b' => value.push('\u{8}'),
+                    'f' => value.push('\u{c}'),
+                    'u' => {
+                        let mut code = String::new();
+                        for _ in 0..4 {
+                            code.push(self.next()?);
+                        }
+                        let c = u32::from_str_radix(&code, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or(ParseJsonError {
+                                kind: JsonErrorKind::UnexpectedCharacter,
+                            })?;
+                        value.push(c);
+                    }
+                    c => value.push(c),
+                },
+                c => value.push(c),
+            }
+        }
+    }
+}
+
+fn size_json(size: Size) -> Json {
+    match size {
+        Size::Small => Json::string("small"),
+        Size::Big => Json::string("big"),
+    }
+}
+
+fn piece_json(piece: &Piece) -> Json {
+    Json::object(vec![
+        ("owner", Json::number(piece.owner)),
+        ("size", size_json(piece.size)),
+    ])
+}
+
+//Event coordinates are already the "x,y" the players type, so they are written out as they are
+pub fn coordinate_json(coordinate: Coordinate) -> Json {
+    Json::object(vec![
+        ("x", Json::number(coordinate.x)),
+        ("y", Json::number(coordinate.y)),
+    ])
+}
+
+pub fn event_json(event: &MoveEvent) -> Json {
+    match event {
+        MoveEvent::Placed { piece, at } => Json::object(vec![
+            ("event", Json::string("Placed")),
+            ("piece", piece_json(piece)),
+            ("at", coordinate_json(*at)),
+        ]),
+        MoveEvent::Booped { from, to } => Json::object(vec![
+            ("event", Json::string("Booped")),
+            ("from", coordinate_json(*from)),
+            ("to", coordinate_json(*to)),
+        ]),
+        MoveEvent::BoopedOff { piece, from } => Json::object(vec![
+            ("event", Json::string("BoopedOff")),
+            ("piece", piece_json(piece)),
+            ("from", coordinate_json(*from)),
+        ]),
+        MoveEvent::LineFormed(line) => Json::object(vec![
+            ("event", Json::string("LineFormed")),
+            (
+                "line",
+                Json::Array(line.0.iter().copied().map(coordinate_json).collect()),
+            ),
+        ]),
+        MoveEvent::Graduated { owner } => Json::object(vec![
+            ("event", Json::string("Graduated")),
+            ("owner", Json::number(*owner)),
+        ]),
+        MoveEvent::Won { winner } => Json::object(vec![
+            ("event", Json::string("Won")),
+            ("winner", Json::number(*winner)),
+        ]),
+        MoveEvent::Swapped => Json::object(vec![("event", Json::string("Swapped"))]),
+        MoveEvent::Passed { player } => Json::object(vec![
+            ("event", Json::string("Passed")),
+            ("player", Json::number(*player)),
+        ]),
+    }
+}
+
+pub fn state_json(game_state: &GameState) -> Json {
+    let mut board = Vec::new();
+    let rules = &game_state.rules;
+    for y in PADDING..PADDING + rules.height {
+        let mut row = String::new();
+        for x in PADDING..PADDING + rules.width {
+            match &game_state.game_board[x][y] {
+                Cell::Piece(piece) => row.push(piece.glyph()),
+                _ => row.push('.'),
+            }
+        }
+        board.push(Json::String(row));
+    }
+
+    let pools = game_state
+        .turn_order
+        .iter()
+        .map(|player| {
+            let (kittens, cats) = player.pool_counts();
+            Json::object(vec![
+                ("name", Json::string(player.name.clone())),
+                ("kittens", Json::number(kittens)),
+                ("cats", Json::number(cats)),
+            ])
+        })
+        .collect();
+
+    let (status, winner) = match game_state.winner {
+        Some(winner) => ("won", Json::number(winner)),
+        None if game_state.nobody_can_move() => ("drawn", Json::Null),
+        None => ("playing", Json::Null),
+    };
+
+    Json::object(vec![
+        ("type", Json::string("state")),
+        ("board", Json::Array(board)),
+        ("pools", Json::Array(pools)),
+        ("to_move", Json::number(game_state.to_move())),
+        ("status", Json::string(status)),
+        ("winner", winner),
+        ("notation", Json::string(game_state.notation())),
+        (
+            "events",
+            Json::Array(game_state.last_events.iter().map(event_json).collect()),
+        ),
+    ])
+}
+
+pub fn error_json(error: &str, kind: &str, message: &str, cause: Option<Json>) -> Json {
+    let mut fields = vec![
+        ("error", Json::string(error)),
+        ("kind", Json::string(kind)),
+        ("message", Json::string(message)),
+    ];
+    if let Some(cause) = cause {
+        fields.push(("cause", cause));
+    }
+    Json::object(fields)
+}
+
+//Top level error replies are tagged like states, the causes nested inside them are not
+pub fn error_reply(error: Json) -> Json {
+    match error {
+        Json::Object(mut fields) => {
+            fields.insert(0, (String::from("type"), Json::string("error")));
+            Json::Object(fields)
+        }
+        error => error,
+    }
+}
+
+fn int_error_json(error: &std::num::ParseIntError) -> Json {
+    Json::object(vec![
+        ("error", Json::string("ParseIntError")),
+        ("kind", Json::string(format!("{:?}", error.kind()))),
+        ("message", Json::string(error.to_string())),
+    ])
+}
+
+fn size_error_json(error: &ParseSizeError) -> Json {
+    let kind = match error.kind {
+        SizeErrorKind::Empty => "Empty",
+        SizeErrorKind::UnknownValue => "UnknownValue",
+    };
+    error_json("ParseSizeError", kind, error.__description(), None)
+}
+
+fn coordinate_error_json(error: &ParseCoordinateError) -> Json {
+    let (kind, cause) = match &error.kind {
+        CoordinateErrorKind::Empty => ("Empty", None),
+        CoordinateErrorKind::InvalidFormat => ("InvalidFormat", None),
+        CoordinateErrorKind::ValueErrorX(cause) => ("ValueErrorX", Some(int_error_json(cause))),
+        CoordinateErrorKind::ValueErrorY(cause) => ("ValueErrorY", Some(int_error_json(cause))),
+    };
+    error_json("ParseCoordinateError", kind, error.__description(), cause)
+}
+
+pub fn placement_error_json(error: &ParsePiecePlacementError) -> Json {
+    let (kind, cause) = match &error.kind {
+        PiecePlacementErrorKind::Empty => ("Empty", None),
+        PiecePlacementErrorKind::InvalidFormat => ("InvalidFormat", None),
+        PiecePlacementErrorKind::ValueErrorSize(cause) => {
+            ("ValueErrorSize", Some(size_error_json(cause)))
+        }
+        PiecePlacementErrorKind::ValueErrorCoordinate(cause) => {
+            ("ValueErrorCoordinate", Some(coordinate_error_json(cause)))
+        }
+    };
+    error_json(
+        "ParsePiecePlacementError",
+        kind,
+        error.__description(),
+        cause,
+    )
+}
+
+pub fn place_error_json(error: &PlacePieceError) -> Json {
+    let kind = match error.kind {
+        PieceErrorKind::OutOfBoundsX => "OutOfBoundsX",
+        PieceErrorKind::OutOfBoundsY => "OutOfBoundsY",
+        PieceErrorKind::PositionOccupied => "PositionOccupied",
+        PieceErrorKind::MissingPiece => "MissingPiece",
+        PieceErrorKind::MissingLineChoice => "MissingLineChoice",
+        PieceErrorKind::InvalidLineChoice => "InvalidLineChoice",
+        PieceErrorKind::GameOver => "GameOver",
+        PieceErrorKind::SwapNotAllowed => "SwapNotAllowed",
+    };
+    error_json("PlacePieceError", kind, error.__description(), None)
+}
+
+pub fn json_error_json(error: &ParseJsonError) -> Json {
+    let kind = match error.kind {
+        JsonErrorKind::Empty => "Empty",
+        JsonErrorKind::UnexpectedEnd => "UnexpectedEnd",
+        JsonErrorKind::UnexpectedCharacter => "UnexpectedCharacter",
+        JsonErrorKind::TrailingCharacters => "TrailingCharacters",
+        JsonErrorKind::TooDeep => "TooDeep",
+    };
+    error_json("ParseJsonError", kind, error.__description(), None)
+}
+
+pub fn move_json(player_move: &Move) -> Json {
+    let Move::Place {
+        placement: PiecePlacement { coordinate, size },
+        line,
+    } = player_move
+    else {
+        return Json::object(vec![
+            ("swap", Json::Bool(true)),
+            ("notation", Json::string(player_move.to_string())),
+        ]);
+    };
+    let line = match line {
+        Some(line) => Json::object(vec![
+            ("x", Json::number(line.x)),
+            ("y", Json::number(line.y)),
+        ]),
+        None => Json::Null,
+    };
+    Json::object(vec![
+        ("size", size_json(*size)),
+        ("x", Json::number(coordinate.x)),
+        ("y", Json::number(coordinate.y)),
+        ("line", line),
+        ("notation", Json::string(player_move.to_string())),
+    ])
+}
+
+//Numbers go through the same usize parsing as "x,y" so bad values report a real ParseIntError
+fn coordinate_from_json(value: &Json) -> Result<Coordinate, ParseCoordinateError> {
+    let (Some(Json::Number(x)), Some(Json::Number(y))) = (value.get("x"), value.get("y")) else {
+        return Err(ParseCoordinateError {
+            kind: CoordinateErrorKind::InvalidFormat,
+        });
+    };
+    let x = x.parse::<usize>().map_err(|error| ParseCoordinateError {
+        kind: CoordinateErrorKind::ValueErrorX(error),
+    })?;
+    let y = y.parse::<usize>().map_err(|error| ParseCoordinateError {
+        kind: CoordinateErrorKind::ValueErrorY(error),
+    })?;
+    Ok(Coordinate { x, y })
+}
+
+//{"size": "small", "x": 2, "y": 3} with an optional "line": {"x": 1, "y": 3}, or {"swap": true}
+pub fn move_from_json(value: &Json) -> Result<Move, ParsePiecePlacementError> {
+    if value.get("swap") == Some(&Json::Bool(true)) {
+        return Ok(Move::Swap);
+    }
+    let Some(Json::String(size)) = value.get("size") else {
+        return Err(ParsePiecePlacementError {
+            kind: PiecePlacementErrorKind::InvalidFormat,
+        });
+    };
+    let size = size
+        .parse::<Size>()
+        .map_err(|error| ParsePiecePlacementError {
+            kind: PiecePlacementErrorKind::ValueErrorSize(error),
+        })?;
+    let coordinate_error = |error| ParsePiecePlacementError {
+        kind: PiecePlacementErrorKind::ValueErrorCoordinate(error),
+    };
+    let coordinate = coordinate_from_json(value).map_err(coordinate_error)?;
+    let line = match value.get("line") {
+        None | Some(Json::Null) => None,
+        Some(line) => Some(coordinate_from_json(line).map_err(coordinate_error)?),
+    };
+
+    Ok(Move::Place {
+        placement: PiecePlacement { coordinate, size },
+        line,
+    })
+}
+
+//The game is played under the rules picked on the command line, the standard game by default
+pub fn run(rules: RuleSet) -> io::Result<()> {
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    run_protocol(stdin.lock(), &mut stdout, rules)
+}
+
+fn run_protocol(input: impl BufRead, output: &mut impl Write, rules: RuleSet) -> io::Result<()> {
+    let mut game_state = GameState::with_rules(rules);
+    writeln!(output, "{}", state_json(&game_state))?;
+
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = match line.parse::<Json>() {
+            Err(error) => error_reply(json_error_json(&error)),
+            Ok(value) => match move_from_json(&value) {
+                Err(error) => error_reply(placement_error_json(&error)),
+                Ok(player_move) => match game_state.play(&player_move) {
+                    Err(error) => error_reply(place_error_json(&error)),
+                    Ok(_) => state_json(&game_state),
+                },
+            },
+        };
+        writeln!(output, "{}", reply)?;
+        output.flush()?;
+
+        if game_state.winner.is_some() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replies(input: &str, rules: RuleSet) -> Vec<Json> {
+        let mut output = Vec::new();
+        run_protocol(input.as_bytes(), &mut output, rules).unwrap();
+        String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| line.parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn rejects_json_nested_too_deeply() {
+        let nested = format!("{}{}", "[".repeat(MAX_DEPTH), "]".repeat(MAX_DEPTH));
+        assert!(nested.parse::<Json>().is_ok());
+        let too_deep = format!("{}{}", "[".repeat(MAX_DEPTH + 1), "]".repeat(MAX_DEPTH + 1));
+        assert_eq!(
+            too_deep.parse::<Json>().unwrap_err().to_string(),
+            "json nested too deeply"
+        );
+    }
+
+    //A row of four where Player 1 is left with one piece on the board and nothing to place after
+    //the fourth move, while Player 2 graduates and plays on
+    fn stuck_player_rules() -> RuleSet {
+        RuleSet {
+            width: 4,
+            height: 1,
+            line_length: 2,
+            pieces_per_player: 2,
+            cat_cap: 2,
+            booped_off_to_pool: false,
+            ..RuleSet::default()
+        }
+    }
+
+    #[test]
+    fn passes_a_player_with_nothing_to_place() {
+        let input = [0, 2, 3, 2]
+            .map(|x| format!("{{\"size\":\"small\",\"x\":{},\"y\":0}}\n", x))
+            .concat();
+        let states = replies(&input, stuck_player_rules());
+        assert_eq!(states[4].get("to_move"), Some(&Json::number(1)));
+        assert_eq!(states[4].get("status"), Some(&Json::string("playing")));
+        let Some(Json::Array(events)) = states[4].get("events") else {
+            panic!("no events");
+        };
+        assert_eq!(
+            events.last(),
+            Some(&"{\"event\":\"Passed\",\"player\":0}".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn reports_draw_when_nobody_can_move() {
+        let rules = RuleSet {
+            booped_off_to_pool: false,
+            ..RuleSet::default()
+        };
+        let game_state =
+            GameState::from_notation("x...../....../....../....../....../...... x - -", rules)
+                .unwrap();
+        assert_eq!(
+            state_json(&game_state).get("status"),
+            Some(&Json::string("drawn"))
+        );
+    }
+
+    #[test]
+    fn plays_under_the_rules_given() {
+        let input = "{\"size\":\"small\",\"x\":2,\"y\":3}\n{\"swap\":true}\n";
+        let standard = replies(input, RuleSet::default());
+        assert_eq!(
+            standard[2].get("kind"),
+            Some(&Json::string("SwapNotAllowed"))
+        );
+
+        let swap = RuleSet {
+            swap_rule: true,
+            ..RuleSet::default()
+        };
+        let swapped = replies(input, swap);
+        assert_eq!(swapped[2].get("type"), Some(&Json::string("state")));
+        assert_eq!(
+            swapped[2].get("events"),
+            Some(&"[{\"event\":\"Swapped\"}]".parse().unwrap())
+        );
+    }
+}
@@ -1,4 +1,21 @@
-use std::{fmt, num::ParseIntError};
+use std::{error::Error, fmt, io::IsTerminal, num::ParseIntError};
+
+use clock::{Clock, SystemTime, TimeControl, TimeSource};
+use handicap::Handicap;
+
+mod ai;
+mod clock;
+mod engine;
+mod generator;
+mod handicap;
+mod json;
+mod net;
+mod puzzle;
+mod review;
+mod rules;
+mod server;
+mod tui;
+mod websocket;
 
 //Error structures designed to mimic std::num::ParseIntError
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -22,6 +39,12 @@ impl CheckCellError {
         }
     }
 }
+impl fmt::Display for CheckCellError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.__description().fmt(f)
+    }
+}
+impl Error for CheckCellError {}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct PlacePieceError {
@@ -33,17 +56,35 @@ enum PieceErrorKind {
     OutOfBoundsY,
     PositionOccupied,
     MissingPiece,
+    MissingLineChoice,
+    InvalidLineChoice,
+    GameOver,
+    SwapNotAllowed,
 }
 impl PlacePieceError {
     fn __description(&self) -> &str {
         match self.kind {
             PieceErrorKind::OutOfBoundsX => "x value out of bounds",
             PieceErrorKind::OutOfBoundsY => "y value out of bounds",
-            PieceErrorKind::PositionOccupied => "attempting to place piece at accupied position",
+            PieceErrorKind::PositionOccupied => "attempting to place piece at occupied position",
             PieceErrorKind::MissingPiece => "attempting to place piece that is not in piece pool",
+            PieceErrorKind::MissingLineChoice => {
+                "several lines of three formed, the move must say which one graduates"
+            }
+            PieceErrorKind::InvalidLineChoice => "chosen piece is not part of a line of three",
+            PieceErrorKind::GameOver => "the game is already over",
+            PieceErrorKind::SwapNotAllowed => {
+                "swapping is only allowed as Player 2's first move when the swap rule is in play"
+            }
         }
     }
 }
+impl fmt::Display for PlacePieceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.__description().fmt(f)
+    }
+}
+impl Error for PlacePieceError {}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct ParseCoordinateError {
@@ -71,6 +112,16 @@ impl fmt::Display for ParseCoordinateError {
         self.__description().fmt(f)
     }
 }
+impl Error for ParseCoordinateError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            CoordinateErrorKind::ValueErrorX(error) | CoordinateErrorKind::ValueErrorY(error) => {
+                Some(error)
+            }
+            _ => None,
+        }
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct ParseSizeError {
@@ -96,6 +147,7 @@ impl fmt::Display for ParseSizeError {
         self.__description().fmt(f)
     }
 }
+impl Error for ParseSizeError {}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct ParsePiecePlacementError {
@@ -125,17 +177,449 @@ impl fmt::Display for ParsePiecePlacementError {
         self.__description().fmt(f)
     }
 }
+impl Error for ParsePiecePlacementError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            PiecePlacementErrorKind::ValueErrorSize(error) => Some(error),
+            PiecePlacementErrorKind::ValueErrorCoordinate(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ParseNotationError {
+    kind: NotationErrorKind,
+}
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum NotationErrorKind {
+    Empty,
+    InvalidFormat,
+    InvalidBoard,
+    InvalidSideToMove,
+    InvalidPool,
+    ImpossiblePosition(InvalidGameStateError),
+}
+impl ParseNotationError {
+    fn __description(&self) -> &str {
+        match self.kind {
+            NotationErrorKind::Empty => "cannot parse position from empty string",
+            NotationErrorKind::InvalidFormat => {
+                "invalid format, should be \"board side_to_move pool pool\" with a pool for every player"
+            }
+            NotationErrorKind::InvalidBoard => {
+                "invalid board, should be one row of cells from \".xXoO\" per board row separated by \"/\""
+            }
+            NotationErrorKind::InvalidSideToMove => {
+                "invalid side to move, should be the kitten of a player like \"x\" or \"o\""
+            }
+            NotationErrorKind::InvalidPool => {
+                "invalid pool, should be the player's pieces like \"xxX\" or \"-\" when empty"
+            }
+            NotationErrorKind::ImpossiblePosition(_) => "position cannot happen in a game",
+        }
+    }
+}
+impl fmt::Display for ParseNotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.__description().fmt(f)
+    }
+}
+impl Error for ParseNotationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            NotationErrorKind::ImpossiblePosition(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct InvalidRuleSetError {
+    kind: RuleSetErrorKind,
+}
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum RuleSetErrorKind {
+    PlayerCount,
+    BoardTooBig,
+    LineTooShort,
+    LineTooLong,
+    ToroidalBoardTooSmall,
+    TooFewPieces,
+    TooManyPieces,
+    TooManyStartingCats,
+    SwapNeedsTwoPlayers,
+    NoWinCondition,
+    Unwinnable,
+}
+impl InvalidRuleSetError {
+    fn __description(&self) -> &str {
+        match self.kind {
+            RuleSetErrorKind::PlayerCount => "boop is played by 2 to 4 players",
+            RuleSetErrorKind::BoardTooBig => "board is more than 32 cells across",
+            RuleSetErrorKind::LineTooShort => "lines need at least 2 pieces",
+            RuleSetErrorKind::LineTooLong => "lines are longer than the board",
+            RuleSetErrorKind::ToroidalBoardTooSmall => {
+                "toroidal boards need at least 3 cells each way and lines that fit both ways"
+            }
+            RuleSetErrorKind::TooFewPieces => "players have fewer pieces than a line needs",
+            RuleSetErrorKind::TooManyPieces => "there are more pieces than cells on the board",
+            RuleSetErrorKind::TooManyStartingCats => {
+                "players start with more cats than they have pieces or the cat cap allows"
+            }
+            RuleSetErrorKind::SwapNeedsTwoPlayers => "the swap rule is for two player games",
+            RuleSetErrorKind::NoWinCondition => "the rules need at least one way to win",
+            RuleSetErrorKind::Unwinnable => "the cat cap is too low for any way to win",
+        }
+    }
+}
+impl fmt::Display for InvalidRuleSetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.__description().fmt(f)
+    }
+}
+impl Error for InvalidRuleSetError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct InvalidGameStateError {
+    kind: GameStateErrorKind,
+}
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum GameStateErrorKind {
+    InvalidRules(InvalidRuleSetError),
+    WrongPlayerCount,
+    MissingPadding,
+    OutOfBoundsOnBoard,
+    UnknownOwner,
+    PoolOwnerMismatch,
+    WrongPieceCount,
+    TooManyCats,
+    UnknownWinner,
+    LineOnBoard,
+}
+impl InvalidGameStateError {
+    fn __description(&self) -> &str {
+        match self.kind {
+            GameStateErrorKind::InvalidRules(_) => "position is played under impossible rules",
+            GameStateErrorKind::WrongPlayerCount => "number of players does not match the rules",
+            GameStateErrorKind::MissingPadding => "cells around the board must be out of bounds",
+            GameStateErrorKind::OutOfBoundsOnBoard => "out of bounds cell inside the board",
+            GameStateErrorKind::UnknownOwner => "piece belongs to a player who is not playing",
+            GameStateErrorKind::PoolOwnerMismatch => "piece pool holds another player's piece",
+            GameStateErrorKind::WrongPieceCount => {
+                "player does not have the number of pieces the rules give them"
+            }
+            GameStateErrorKind::TooManyCats => "player owns more cats than the rules allow",
+            GameStateErrorKind::UnknownWinner => "winner is not one of the players",
+            GameStateErrorKind::LineOnBoard => {
+                "line on the board that should already have graduated"
+            }
+        }
+    }
+}
+impl fmt::Display for InvalidGameStateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.__description().fmt(f)
+    }
+}
+impl Error for InvalidGameStateError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            GameStateErrorKind::InvalidRules(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+//A file named on the command line that could not be read
+#[derive(Debug)]
+struct ReadFileError {
+    path: String,
+    error: std::io::Error,
+}
+impl fmt::Display for ReadFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cannot read {}", self.path)
+    }
+}
+impl Error for ReadFileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+//A rules, game or puzzle file that was read but holds something that can't be used
+#[derive(Debug)]
+struct LoadFileError {
+    path: String,
+    kind: LoadFileErrorKind,
+}
+#[derive(Debug)]
+enum LoadFileErrorKind {
+    Rules(rules::ParseRulesError),
+    Game(ParseNotationError),
+    Puzzles(puzzle::ParsePuzzleError),
+}
+impl LoadFileError {
+    fn __description(&self) -> &str {
+        match self.kind {
+            LoadFileErrorKind::Rules(_) => "cannot load rules from",
+            LoadFileErrorKind::Game(_) => "cannot load game from",
+            LoadFileErrorKind::Puzzles(_) => "cannot load puzzles from",
+        }
+    }
+}
+impl fmt::Display for LoadFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.__description(), self.path)
+    }
+}
+impl Error for LoadFileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            LoadFileErrorKind::Rules(error) => Some(error),
+            LoadFileErrorKind::Game(error) => Some(error),
+            LoadFileErrorKind::Puzzles(error) => Some(error),
+        }
+    }
+}
+
+//Command line flags that are missing their value or can't be used together
+#[derive(Debug)]
+struct ArgumentError {
+    kind: ArgumentErrorKind,
+}
+#[derive(Debug)]
+enum ArgumentErrorKind {
+    MissingValue { flag: String, usage: &'static str },
+    RulesFileConflict,
+    PlayerCount,
+    SeveralStarts,
+    InvalidPosition(ParseNotationError),
+    InvalidHandicap(InvalidGameStateError),
+}
+impl ArgumentError {
+    fn __description(&self) -> &str {
+        match self.kind {
+            ArgumentErrorKind::MissingValue { .. } => "missing or invalid value after flag",
+            ArgumentErrorKind::RulesFileConflict => {
+                "set players, pieces_per_player, toroidal and swap_rule in the rules file instead"
+            }
+            ArgumentErrorKind::PlayerCount => "unsupported number of players",
+            ArgumentErrorKind::SeveralStarts => {
+                "only one of --load, --position and --handicap can be given"
+            }
+            ArgumentErrorKind::InvalidPosition(_) => "invalid position",
+            ArgumentErrorKind::InvalidHandicap(_) => "invalid handicap",
+        }
+    }
+}
+//Shows the usage of a flag missing its value and the supported player counts, which the
+//description alone can't
+impl fmt::Display for ArgumentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ArgumentErrorKind::MissingValue { flag, usage } => {
+                write!(f, "usage: boop_game {} {}", flag, usage)
+            }
+            ArgumentErrorKind::PlayerCount => {
+                write!(f, "boop is played by 2 to {} players", MAX_PLAYERS)
+            }
+            _ => self.__description().fmt(f),
+        }
+    }
+}
+impl Error for ArgumentError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            ArgumentErrorKind::InvalidPosition(error) => Some(error),
+            ArgumentErrorKind::InvalidHandicap(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+//Every error the game can produce, for callers that handle them all the same way.
+//Display and source are those of the wrapped error
+#[derive(Debug)]
+enum BoopError {
+    CheckCell(CheckCellError),
+    PlacePiece(PlacePieceError),
+    ParseCoordinate(ParseCoordinateError),
+    ParseSize(ParseSizeError),
+    ParsePiecePlacement(ParsePiecePlacementError),
+    ParseNotation(ParseNotationError),
+    InvalidGameState(InvalidGameStateError),
+    InvalidRuleSet(InvalidRuleSetError),
+    ParseRules(rules::ParseRulesError),
+    ParseJson(json::ParseJsonError),
+    ParseTimeControl(clock::ParseTimeControlError),
+    ParseHandicap(handicap::ParseHandicapError),
+    InvalidPuzzle(puzzle::InvalidPuzzleError),
+    ParsePuzzle(puzzle::ParsePuzzleError),
+    ReadFile(ReadFileError),
+    LoadFile(LoadFileError),
+    Argument(ArgumentError),
+}
+impl BoopError {
+    fn inner(&self) -> &(dyn Error + 'static) {
+        match self {
+            BoopError::CheckCell(error) => error,
+            BoopError::PlacePiece(error) => error,
+            BoopError::ParseCoordinate(error) => error,
+            BoopError::ParseSize(error) => error,
+            BoopError::ParsePiecePlacement(error) => error,
+            BoopError::ParseNotation(error) => error,
+            BoopError::InvalidGameState(error) => error,
+            BoopError::InvalidRuleSet(error) => error,
+            BoopError::ParseRules(error) => error,
+            BoopError::ParseJson(error) => error,
+            BoopError::ParseTimeControl(error) => error,
+            BoopError::ParseHandicap(error) => error,
+            BoopError::InvalidPuzzle(error) => error,
+            BoopError::ParsePuzzle(error) => error,
+            BoopError::ReadFile(error) => error,
+            BoopError::LoadFile(error) => error,
+            BoopError::Argument(error) => error,
+        }
+    }
+}
+impl fmt::Display for BoopError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.inner(), f)
+    }
+}
+impl Error for BoopError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.inner().source()
+    }
+}
+impl From<CheckCellError> for BoopError {
+    fn from(error: CheckCellError) -> Self {
+        BoopError::CheckCell(error)
+    }
+}
+impl From<PlacePieceError> for BoopError {
+    fn from(error: PlacePieceError) -> Self {
+        BoopError::PlacePiece(error)
+    }
+}
+impl From<ParseCoordinateError> for BoopError {
+    fn from(error: ParseCoordinateError) -> Self {
+        BoopError::ParseCoordinate(error)
+    }
+}
+impl From<ParseSizeError> for BoopError {
+    fn from(error: ParseSizeError) -> Self {
+        BoopError::ParseSize(error)
+    }
+}
+impl From<ParsePiecePlacementError> for BoopError {
+    fn from(error: ParsePiecePlacementError) -> Self {
+        BoopError::ParsePiecePlacement(error)
+    }
+}
+impl From<ParseNotationError> for BoopError {
+    fn from(error: ParseNotationError) -> Self {
+        BoopError::ParseNotation(error)
+    }
+}
+impl From<InvalidGameStateError> for BoopError {
+    fn from(error: InvalidGameStateError) -> Self {
+        BoopError::InvalidGameState(error)
+    }
+}
+impl From<InvalidRuleSetError> for BoopError {
+    fn from(error: InvalidRuleSetError) -> Self {
+        BoopError::InvalidRuleSet(error)
+    }
+}
+impl From<rules::ParseRulesError> for BoopError {
+    fn from(error: rules::ParseRulesError) -> Self {
+        BoopError::ParseRules(error)
+    }
+}
+impl From<json::ParseJsonError> for BoopError {
+    fn from(error: json::ParseJsonError) -> Self {
+        BoopError::ParseJson(error)
+    }
+}
+impl From<clock::ParseTimeControlError> for BoopError {
+    fn from(error: clock::ParseTimeControlError) -> Self {
+        BoopError::ParseTimeControl(error)
+    }
+}
+impl From<handicap::ParseHandicapError> for BoopError {
+    fn from(error: handicap::ParseHandicapError) -> Self {
+        BoopError::ParseHandicap(error)
+    }
+}
+impl From<puzzle::InvalidPuzzleError> for BoopError {
+    fn from(error: puzzle::InvalidPuzzleError) -> Self {
+        BoopError::InvalidPuzzle(error)
+    }
+}
+impl From<puzzle::ParsePuzzleError> for BoopError {
+    fn from(error: puzzle::ParsePuzzleError) -> Self {
+        BoopError::ParsePuzzle(error)
+    }
+}
+impl From<ReadFileError> for BoopError {
+    fn from(error: ReadFileError) -> Self {
+        BoopError::ReadFile(error)
+    }
+}
+impl From<LoadFileError> for BoopError {
+    fn from(error: LoadFileError) -> Self {
+        BoopError::LoadFile(error)
+    }
+}
+impl From<ArgumentError> for BoopError {
+    fn from(error: ArgumentError) -> Self {
+        BoopError::Argument(error)
+    }
+}
+
+//Reads a whole file named on the command line
+fn read_file(path: &str) -> Result<String, ReadFileError> {
+    std::fs::read_to_string(path).map_err(|error| ReadFileError {
+        path: path.to_string(),
+        error,
+    })
+}
+
+//An error followed by everything that caused it, like
+//"problem parsing coordinate value: problem parsing y value: invalid digit found in string"
+fn error_chain(error: &dyn Error) -> String {
+    let mut chain = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        chain.push_str(": ");
+        chain.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    chain
+}
 
 #[derive(Copy, Clone, PartialEq, Debug, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 enum Size {
     Small,
     Big,
 }
+impl fmt::Display for Size {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Size::Small => "s".fmt(f),
+            Size::Big => "b".fmt(f),
+        }
+    }
+}
 impl std::str::FromStr for Size {
     type Err = ParseSizeError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.to_lowercase().as_str() {
+        match s.trim().to_lowercase().as_str() {
             "" => Err(ParseSizeError {
                 kind: SizeErrorKind::Empty,
             }),
@@ -149,132 +633,264 @@ impl std::str::FromStr for Size {
 }
 
 #[derive(Clone, PartialEq, Debug, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct Piece {
     owner: usize,
     size: Size,
 }
 
-#[derive(PartialEq, Debug)]
+//Kitten glyph for each player's pieces, indexed by owner. Cats are the same letter in capitals
+const PLAYER_GLYPHS: [char; 4] = ['x', 'o', 'v', 'z'];
+const MAX_PLAYERS: usize = PLAYER_GLYPHS.len();
+
+impl Piece {
+    fn glyph(&self) -> char {
+        let glyph = PLAYER_GLYPHS[self.owner];
+        match self.size {
+            Size::Small => glyph,
+            Size::Big => glyph.to_ascii_uppercase(),
+        }
+    }
+
+    fn from_glyph(glyph: char) -> Option<Piece> {
+        let owner = PLAYER_GLYPHS
+            .iter()
+            .position(|player_glyph| *player_glyph == glyph.to_ascii_lowercase())?;
+        let size = if glyph.is_ascii_uppercase() {
+            Size::Big
+        } else {
+            Size::Small
+        };
+        Some(Piece { owner, size })
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct Player {
     name: String,
     piece_pool: Vec<Piece>,
 }
+impl Player {
+    //Number of (kittens, cats) waiting in the pool
+    fn pool_counts(&self) -> (usize, usize) {
+        let kittens = self
+            .piece_pool
+            .iter()
+            .filter(|p| p.size == Size::Small)
+            .count();
+        (kittens, self.piece_pool.len() - kittens)
+    }
+}
 
 #[derive(Clone, PartialEq, Debug, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 enum Cell {
     OutOfBounds,
     Empty,
     Piece(Piece),
 }
 
-#[derive(Debug)]
-struct GameState {
-    game_board: [[Cell; 10]; 10],
-    turn_order: [Player; 2],
-    turn_count: usize,
+//OutOfBounds cells around every side of the board. A boop looks two cells out from the placed
+//piece, so this much padding lets it run off the edge without any bounds checks
+const PADDING: usize = 2;
+
+//The parts of the game variants change. The default is the standard game: two players on a 6x6
+//board with 8 kittens each and lines of three, where a line of three cats wins
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+struct RuleSet {
+    players: usize,
+    width: usize,
+    height: usize,
+    pieces_per_player: usize,
+    line_length: usize,
+    //most cats a player may own, kittens graduating past it come back as kittens
+    cat_cap: usize,
+    //the edges wrap around: boops and lines carry on from the opposite edge
+    toroidal: bool,
+    //how many of each player's pieces start in the pool as cats rather than kittens
+    starting_cats: usize,
+    cats_boop_cats: bool,
+    //otherwise pieces booped off the board are out of the game
+    booped_off_to_pool: bool,
+    cat_line_wins: bool,
+    //a player whose pieces are all cats on the board wins
+    all_cats_on_board_wins: bool,
+    //the pie rule: Player 2 can take over Player 1's opening piece instead of making their own
+    //first move
+    swap_rule: bool,
 }
-impl GameState {
-    #[rustfmt::skip]
-    fn init() -> GameState {
-        GameState {
-            game_board: [
-                [ Cell::OutOfBounds, Cell::OutOfBounds, Cell::OutOfBounds, Cell::OutOfBounds, Cell::OutOfBounds, Cell::OutOfBounds, Cell::OutOfBounds, Cell::OutOfBounds, Cell::OutOfBounds, Cell::OutOfBounds,],
-                [ Cell::OutOfBounds, Cell::OutOfBounds, Cell::OutOfBounds, Cell::OutOfBounds, Cell::OutOfBounds, Cell::OutOfBounds, Cell::OutOfBounds, Cell::OutOfBounds, Cell::OutOfBounds, Cell::OutOfBounds,],
-                [ Cell::OutOfBounds, Cell::OutOfBounds, Cell::Empty,       Cell::Empty,       Cell::Empty,       Cell::Empty,       Cell::Empty,       Cell::Empty,       Cell::OutOfBounds, Cell::OutOfBounds,],
-                [ Cell::OutOfBounds, Cell::OutOfBounds, Cell::Empty,       Cell::Empty,       Cell::Empty,       Cell::Empty,       Cell::Empty,       Cell::Empty,       Cell::OutOfBounds, Cell::OutOfBounds,],
-                [ Cell::OutOfBounds, Cell::OutOfBounds, Cell::Empty,       Cell::Empty,       Cell::Empty,       Cell::Empty,       Cell::Empty,       Cell::Empty,       Cell::OutOfBounds, Cell::OutOfBounds,],
-                [ Cell::OutOfBounds, Cell::OutOfBounds, Cell::Empty,       Cell::Empty,       Cell::Empty,       Cell::Empty,       Cell::Empty,       Cell::Empty,       Cell::OutOfBounds, Cell::OutOfBounds,],
-                [ Cell::OutOfBounds, Cell::OutOfBounds, Cell::Empty,       Cell::Empty,       Cell::Empty,       Cell::Empty,       Cell::Empty,       Cell::Empty,       Cell::OutOfBounds, Cell::OutOfBounds,],
-                [ Cell::OutOfBounds, Cell::OutOfBounds, Cell::Empty,       Cell::Empty,       Cell::Empty,       Cell::Empty,       Cell::Empty,       Cell::Empty,       Cell::OutOfBounds, Cell::OutOfBounds,],
-                [ Cell::OutOfBounds, Cell::OutOfBounds, Cell::OutOfBounds, Cell::OutOfBounds, Cell::OutOfBounds, Cell::OutOfBounds, Cell::OutOfBounds, Cell::OutOfBounds, Cell::OutOfBounds, Cell::OutOfBounds,],
-                [ Cell::OutOfBounds, Cell::OutOfBounds, Cell::OutOfBounds, Cell::OutOfBounds, Cell::OutOfBounds, Cell::OutOfBounds, Cell::OutOfBounds, Cell::OutOfBounds, Cell::OutOfBounds, Cell::OutOfBounds,],
-            ],
-            turn_order: [
-                Player {
-                    name: String::from("Player 1"),
-                    piece_pool: vec![
-                        Piece { owner: 0, size: Size::Small,},
-                        Piece { owner: 0, size: Size::Small,},
-                        Piece { owner: 0, size: Size::Small,},
-                        Piece { owner: 0, size: Size::Small,},
-                        Piece { owner: 0, size: Size::Small,},
-                        Piece { owner: 0, size: Size::Small,},
-                        Piece { owner: 0, size: Size::Small,},
-                        Piece { owner: 0, size: Size::Small,},
-                    ],
-                },
-                Player {
-                    name: String::from("Player 2"),
-                    piece_pool: vec![
-                        Piece { owner: 1, size: Size::Small,},
-                        Piece { owner: 1, size: Size::Small,},
-                        Piece { owner: 1, size: Size::Small,},
-                        Piece { owner: 1, size: Size::Small,},
-                        Piece { owner: 1, size: Size::Small,},
-                        Piece { owner: 1, size: Size::Small,},
-                        Piece { owner: 1, size: Size::Small,},
-                        Piece { owner: 1, size: Size::Small,},
-                    ],
-                },
-            ],
-            turn_count: 0,
+impl Default for RuleSet {
+    fn default() -> Self {
+        RuleSet {
+            players: 2,
+            width: 6,
+            height: 6,
+            pieces_per_player: 8,
+            line_length: 3,
+            cat_cap: 8,
+            toroidal: false,
+            starting_cats: 0,
+            cats_boop_cats: true,
+            booped_off_to_pool: true,
+            cat_line_wins: true,
+            all_cats_on_board_wins: false,
+            swap_rule: false,
         }
     }
-
-    fn display(&self) {
-        let mut player1pieces = String::new();
-        for p in &self.turn_order[0].piece_pool {
-            match p {
-                Piece { owner: _, size } if size == &Size::Small => player1pieces += "x",
-                Piece { owner: _, size } if size == &Size::Big => player1pieces += "X",
-                _ => panic!(),
-            }
+}
+impl RuleSet {
+    //Standard rules for a bigger table. The standard game's 16 pieces are shared out between the
+    //players so the board doesn't fill up
+    fn for_players(players: usize) -> RuleSet {
+        let pieces_per_player = 16 / players;
+        RuleSet {
+            players,
+            pieces_per_player,
+            cat_cap: pieces_per_player,
+            ..RuleSet::default()
         }
-        println!("{}", player1pieces);
+    }
 
-        for y in 2..8 {
-            let mut row_cells = String::new();
+    //Rejects combinations that can't be played or can never be won
+    fn check(&self) -> Result<(), InvalidRuleSetError> {
+        let error = |kind| Err(InvalidRuleSetError { kind });
+        if !(2..=MAX_PLAYERS).contains(&self.players) {
+            return error(RuleSetErrorKind::PlayerCount);
+        }
+        if self.width > 32 || self.height > 32 {
+            return error(RuleSetErrorKind::BoardTooBig);
+        }
+        if self.line_length < 2 {
+            return error(RuleSetErrorKind::LineTooShort);
+        }
+        if self.line_length > self.width.max(self.height) {
+            return error(RuleSetErrorKind::LineTooLong);
+        }
+        //smaller boards would boop pieces onto themselves and lines would wrap onto themselves
+        if self.toroidal && self.width.min(self.height) < 3.max(self.line_length) {
+            return error(RuleSetErrorKind::ToroidalBoardTooSmall);
+        }
+        if self.pieces_per_player < self.line_length {
+            return error(RuleSetErrorKind::TooFewPieces);
+        }
+        if self.players * self.pieces_per_player > self.width * self.height {
+            return error(RuleSetErrorKind::TooManyPieces);
+        }
+        if self.starting_cats > self.pieces_per_player.min(self.cat_cap) {
+            return error(RuleSetErrorKind::TooManyStartingCats);
+        }
+        if self.swap_rule && self.players != 2 {
+            return error(RuleSetErrorKind::SwapNeedsTwoPlayers);
+        }
+        if !self.cat_line_wins && !self.all_cats_on_board_wins {
+            return error(RuleSetErrorKind::NoWinCondition);
+        }
+        let cat_line_reachable = self.cat_line_wins && self.cat_cap >= self.line_length;
+        let all_cats_reachable =
+            self.all_cats_on_board_wins && self.cat_cap >= self.pieces_per_player;
+        if !cat_line_reachable && !all_cats_reachable {
+            return error(RuleSetErrorKind::Unwinnable);
+        }
+        Ok(())
+    }
 
-            for x in 2..8 {
-                match self.game_board[x][y] {
-                    Cell::Empty => row_cells += "_ ",
-                    Cell::Piece(Piece { owner, size }) if owner == 0 && size == Size::Small => {
-                        row_cells += "x "
-                    }
-                    Cell::Piece(Piece { owner, size }) if owner == 0 && size == Size::Big => {
-                        row_cells += "X "
-                    }
-                    Cell::Piece(Piece { owner, size }) if owner == 1 && size == Size::Small => {
-                        row_cells += "o "
-                    }
-                    Cell::Piece(Piece { owner, size }) if owner == 1 && size == Size::Big => {
-                        row_cells += "O "
-                    }
-                    _ => panic!(),
-                }
-            }
+    //Whether a padded game board coordinate is on the board rather than in the padding
+    fn on_board(&self, coordinate: Coordinate) -> bool {
+        (PADDING..PADDING + self.width).contains(&coordinate.x)
+            && (PADDING..PADDING + self.height).contains(&coordinate.y)
+    }
 
-            println!("{}", row_cells);
+    //The padded coordinate dx,dy away from coordinate. On a toroidal board it wraps around to the
+    //opposite edge, otherwise it can land in the padding. None when it is off the game board
+    //altogether
+    fn offset(&self, coordinate: Coordinate, dx: isize, dy: isize) -> Option<Coordinate> {
+        let x = coordinate.x as isize + dx;
+        let y = coordinate.y as isize + dy;
+        if self.toroidal {
+            let wrap = |value: isize, size: usize| {
+                (value - PADDING as isize).rem_euclid(size as isize) as usize + PADDING
+            };
+            return Some(Coordinate {
+                x: wrap(x, self.width),
+                y: wrap(y, self.height),
+            });
         }
+        Some(Coordinate {
+            x: usize::try_from(x).ok()?,
+            y: usize::try_from(y).ok()?,
+        })
+    }
+
+    //Padded coordinates of every cell on the board, row by row from y=0
+    fn board_cells(&self) -> impl Iterator<Item = Coordinate> {
+        let width = self.width;
+        (PADDING..PADDING + self.height)
+            .flat_map(move |y| (PADDING..PADDING + width).map(move |x| Coordinate { x, y }))
+    }
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "UncheckedGameState"))]
+struct GameState {
+    rules: RuleSet,
+    //indexed [x][y], with PADDING cells of OutOfBounds on every side of the board
+    game_board: Vec<Vec<Cell>>,
+    turn_order: Vec<Player>,
+    turn_count: usize,
+    last_events: Vec<MoveEvent>,
+    winner: Option<usize>,
+}
+impl GameState {
+    fn init() -> GameState {
+        GameState::with_rules(RuleSet::default())
+    }
 
-        let mut player2pieces = String::new();
-        for p in &self.turn_order[1].piece_pool {
-            match p {
-                Piece { owner: _, size } if size == &Size::Small => player2pieces += "o",
-                Piece { owner: _, size } if size == &Size::Big => player2pieces += "O",
-                _ => panic!(),
+    fn with_rules(rules: RuleSet) -> GameState {
+        let mut game_board =
+            vec![vec![Cell::OutOfBounds; rules.height + 2 * PADDING]; rules.width + 2 * PADDING];
+        for coordinate in rules.board_cells() {
+            game_board[coordinate.x][coordinate.y] = Cell::Empty;
+        }
+        let player = |owner: usize| {
+            let mut piece_pool = vec![
+                Piece {
+                    owner,
+                    size: Size::Small,
+                };
+                rules.pieces_per_player
+            ];
+            for piece in piece_pool.iter_mut().take(rules.starting_cats) {
+                piece.size = Size::Big;
             }
+            Player {
+                name: format!("Player {}", owner + 1),
+                piece_pool,
+            }
+        };
+
+        GameState {
+            rules,
+            game_board,
+            turn_order: (0..rules.players).map(player).collect(),
+            turn_count: 0,
+            last_events: Vec::new(),
+            winner: None,
         }
-        println!("{}", player2pieces);
     }
 
-    fn check_cell(&self, coordinate: Coordinate) -> Result<Vec<ThreeInRow>, CheckCellError> {
-        if coordinate.x > 7 || coordinate.x < 2 {
+    //Lines of one player's pieces that start at coordinate and run right, down or diagonally,
+    //wrapping over the edges on a toroidal board
+    fn check_cell(&self, coordinate: Coordinate) -> Result<Vec<Line>, CheckCellError> {
+        if !(PADDING..PADDING + self.rules.width).contains(&coordinate.x) {
             return Result::Err(CheckCellError {
                 kind: CellErrorKind::OutOfBoundsX,
             });
         }
-        if coordinate.y > 7 || coordinate.x < 2 {
+        if !(PADDING..PADDING + self.rules.height).contains(&coordinate.y) {
             return Result::Err(CheckCellError {
                 kind: CellErrorKind::OutOfBoundsY,
             });
@@ -289,45 +905,20 @@ impl GameState {
                 owner: current_owner,
                 size: _,
             }) => {
-                let mut matches: Vec<ThreeInRow> = Vec::new();
-
-                let dirs = [
-                    Coordinate { x: 0, y: 0 },
-                    Coordinate { x: 0, y: 1 },
-                    Coordinate { x: 0, y: 2 },
-                    Coordinate { x: 1, y: 2 },
-                ];
-
-                for d in dirs {
-                    if let Cell::Piece(Piece {
-                        owner: current_owner1,
-                        size: _,
-                    }) = self.game_board[coordinate.x - 1 + d.x][coordinate.y - 1 + d.y]
-                    {
-                        if let Cell::Piece(Piece {
-                            owner: current_owner2,
-                            size: _,
-                        }) = self.game_board[coordinate.x + 1 - d.x][coordinate.y + 1 - d.y]
-                        {
-                            if current_owner == &current_owner1
-                                && &current_owner1 == &current_owner2
-                            {
-                                matches.push(ThreeInRow(
-                                    Coordinate {
-                                        x: coordinate.x - 1 + d.x,
-                                        y: coordinate.y - 1 + d.y,
-                                    },
-                                    Coordinate {
-                                        x: coordinate.x,
-                                        y: coordinate.y,
-                                    },
-                                    Coordinate {
-                                        x: coordinate.x + 1 - d.x,
-                                        y: coordinate.y + 1 - d.y,
-                                    },
-                                ));
+                let mut matches: Vec<Line> = Vec::new();
+
+                for (dx, dy) in [(1, 0), (0, 1), (1, 1), (1, -1)] {
+                    let cells: Vec<Coordinate> = (0..self.rules.line_length as isize)
+                        .map_while(|step| {
+                            let cell = self.rules.offset(coordinate, dx * step, dy * step)?;
+                            match &self.game_board.get(cell.x)?.get(cell.y)? {
+                                Cell::Piece(piece) if piece.owner == *current_owner => Some(cell),
+                                _ => None,
                             }
-                        }
+                        })
+                        .collect();
+                    if cells.len() == self.rules.line_length {
+                        matches.push(Line(cells));
                     }
                 }
                 Result::Ok(matches)
@@ -335,133 +926,231 @@ impl GameState {
         }
     }
 
-    fn check_board(&mut self, constraining_coordinate: Option<Coordinate>) {
-        let mut matches = Vec::new();
-        for row_index in 2..8 {
-            for column_index in 2..8 {
-                matches.extend(
-                    self.check_cell(Coordinate {
-                        x: row_index,
-                        y: column_index,
-                    })
-                    .unwrap(),
-                )
+    //Every line currently on the board
+    fn lines(&self) -> Vec<Line> {
+        let mut matches: Vec<Line> = Vec::new();
+        for coordinate in self.rules.board_cells() {
+            for line in self.check_cell(coordinate).unwrap() {
+                //on a toroidal board a line as long as the board is a ring, which is found again
+                //from every one of its cells
+                if !matches.iter().any(|found| found.same_cells(&line)) {
+                    matches.push(line);
+                }
             }
         }
+        matches
+    }
 
-        let mut constrained_matches: Vec<ThreeInRow> = Vec::new();
-        match constraining_coordinate {
-            None => constrained_matches = matches,
-            Some(constraining_coordinate) => {
-                for m in matches {
-                    let ThreeInRow(c1, c2, c3) = m;
-                    if c1 == constraining_coordinate
-                        || c2 == constraining_coordinate
-                        || c3 == constraining_coordinate
-                    {
-                        constrained_matches.push(m.clone())
-                    }
+    //Graduates the line on the board when there is only one. When there are several nothing is
+    //changed and the candidates are returned so the player can pick a piece to narrow the choice
+    //down
+    fn check_board(&mut self) -> BoardCheck {
+        let matches = self.lines();
+        match matches.len() {
+            //graduating takes pieces off the board, so only a move without lines can fill the
+            //board with a player's cats
+            0 => {
+                let mover = self.to_move();
+                if self.winner.is_some() || !self.all_cats_on_board(mover) {
+                    return BoardCheck::Resolved(Vec::new());
                 }
+                self.winner = Some(mover);
+                let events = vec![MoveEvent::Won { winner: mover }];
+                self.last_events.extend(events.iter().cloned());
+                BoardCheck::Resolved(events)
             }
-        }
-        match constrained_matches.len() {
-            0 => return,
             1 => {
-                if let Cell::Piece(Piece { owner, size: _ }) =
-                    &self.game_board[constrained_matches[0].0.x][constrained_matches[0].0.y]
-                {
-                    if let Cell::Piece(Piece {
-                        owner: _,
-                        size: size0,
-                    }) = self.game_board[constrained_matches[0].0.x][constrained_matches[0].0.y]
-                    {
-                        if let Cell::Piece(Piece {
-                            owner: _,
-                            size: size1,
-                        }) =
-                            self.game_board[constrained_matches[0].1.x][constrained_matches[0].1.y]
-                        {
-                            if let Cell::Piece(Piece {
-                                owner: _,
-                                size: size2,
-                            }) = self.game_board[constrained_matches[0].2.x]
-                                [constrained_matches[0].2.y]
-                            {
-                                if size0 == Size::Big && size1 == Size::Big && size2 == Size::Big {
-                                    win(&self.turn_order[*owner].name)
-                                }
-                            }
-                        }
-                    }
-
-                    self.turn_order[*owner].piece_pool.extend([
-                        Piece {
-                            owner: *owner,
-                            size: Size::Big,
-                        },
-                        Piece {
-                            owner: *owner,
-                            size: Size::Big,
-                        },
-                        Piece {
-                            owner: *owner,
-                            size: Size::Big,
-                        },
-                    ]);
-
-                    self.game_board[constrained_matches[0].0.x][constrained_matches[0].0.y] =
-                        Cell::Empty;
-                    self.game_board[constrained_matches[0].1.x][constrained_matches[0].1.y] =
-                        Cell::Empty;
-                    self.game_board[constrained_matches[0].2.x][constrained_matches[0].2.y] =
-                        Cell::Empty;
-                } else {
-                    panic!("match coordinate not a piece")
-                }
-            }
-            _ => {
-                self.display();
-                let mut position_input = String::new();
-
-                println!("Select constraining Piece");
-                std::io::stdin()
-                    .read_line(&mut position_input)
-                    .expect("Failed to read line");
-
-                let constraining_coordinate: Coordinate = match position_input.trim().parse() {
-                    Result::Ok(ok) => ok,
-                    Result::Err(_) => {
-                        println!("Please input valid position");
-                        Coordinate { x: 99, y: 99 }
-                    }
-                };
-                let constraining_coordinate = Coordinate {
-                    x: constraining_coordinate.x + 2,
-                    y: constraining_coordinate.y + 2,
-                };
-                self.check_board(Some(constraining_coordinate));
+                let events = self.graduate(&matches[0]);
+                self.last_events.extend(events.iter().cloned());
+                BoardCheck::Resolved(events)
             }
+            _ => BoardCheck::Ambiguous(matches),
         }
     }
 
-    fn place_piece(&mut self, piece_placement: PiecePlacement) -> Result<(), PlacePieceError> {
-        let player_index = self.turn_count % self.turn_order.len();
+    //Whether the all cats on board rule makes the player a winner: every piece they have left is
+    //a cat on the board
+    fn all_cats_on_board(&self, owner: usize) -> bool {
+        if !self.rules.all_cats_on_board_wins || !self.turn_order[owner].piece_pool.is_empty() {
+            return false;
+        }
+        let mut pieces = self
+            .rules
+            .board_cells()
+            .filter_map(|c| match &self.game_board[c.x][c.y] {
+                Cell::Piece(piece) if piece.owner == owner => Some(piece.size),
+                _ => None,
+            })
+            .peekable();
+        pieces.peek().is_some() && pieces.all(|size| size == Size::Big)
+    }
+
+    //Number of cats a player owns, on the board and in their pool
+    fn cats_owned(&self, owner: usize) -> usize {
+        let on_board = self
+            .rules
+            .board_cells()
+            .filter(|c| {
+                matches!(
+                    &self.game_board[c.x][c.y],
+                    Cell::Piece(piece) if piece.owner == owner && piece.size == Size::Big
+                )
+            })
+            .count();
+        let (_, in_pool) = self.turn_order[owner].pool_counts();
+        on_board + in_pool
+    }
 
-        let coordinate = Coordinate {
-            x: piece_placement.coordinate.x + 2,
-            y: piece_placement.coordinate.y + 2,
+    //Removes a line from the board, returning its pieces to the pool as cats as long as the
+    //owner is under the cat cap
+    fn graduate(&mut self, line: &Line) -> Vec<MoveEvent> {
+        let mut events = vec![MoveEvent::LineFormed(line.board_coordinates())];
+        let owner = match &self.game_board[line.0[0].x][line.0[0].y] {
+            Cell::Piece(Piece { owner, size: _ }) => *owner,
+            _ => panic!("match coordinate not a piece"),
         };
 
-        if coordinate.x > 7 || coordinate.x < 2 {
+        if self.rules.cat_line_wins
+            && line.0.iter().all(|c| {
+                matches!(
+                    self.game_board[c.x][c.y],
+                    Cell::Piece(Piece {
+                        owner: _,
+                        size: Size::Big,
+                    })
+                )
+            })
+        {
+            self.winner = Some(owner);
+        }
+
+        let mut cats = self.cats_owned(owner);
+        for c in &line.0 {
+            let cell = std::mem::replace(&mut self.game_board[c.x][c.y], Cell::Empty);
+            let size = match cell {
+                Cell::Piece(Piece {
+                    owner: _,
+                    size: Size::Big,
+                }) => Size::Big,
+                _ if cats < self.rules.cat_cap => {
+                    cats += 1;
+                    Size::Big
+                }
+                _ => Size::Small,
+            };
+            self.turn_order[owner]
+                .piece_pool
+                .push(Piece { owner, size });
+        }
+
+        events.push(MoveEvent::Graduated { owner });
+        if let Some(winner) = self.winner {
+            events.push(MoveEvent::Won { winner });
+        }
+        events
+    }
+
+    //Reports what a placement would do without changing the game state
+    fn preview(&self, piece_placement: PiecePlacement) -> Result<Preview, PlacePieceError> {
+        let player_index = self.turn_count % self.turn_order.len();
+        let size = piece_placement.size;
+
+        let mut after = self.clone();
+        after.place_piece(piece_placement.clone())?;
+        let coordinate = piece_placement.coordinate.padded();
+
+        let lines = after.lines();
+        let mut winner = None;
+        for line in lines.iter().filter(|_| self.rules.cat_line_wins) {
+            if line.0.iter().all(|c| {
+                matches!(
+                    after.game_board[c.x][c.y],
+                    Cell::Piece(Piece {
+                        owner: _,
+                        size: Size::Big,
+                    })
+                )
+            }) {
+                if let Cell::Piece(piece) = &after.game_board[line.0[0].x][line.0[0].y] {
+                    if winner != Some(player_index) {
+                        winner = Some(piece.owner);
+                    }
+                }
+            }
+        }
+
+        if lines.is_empty() && after.all_cats_on_board(player_index) {
+            winner = Some(player_index);
+        }
+
+        Result::Ok(Preview {
+            boops: self
+                .boops(coordinate, size)
+                .into_iter()
+                .map(Boop::board_coordinates)
+                .collect(),
+            lines: lines.iter().map(Line::board_coordinates).collect(),
+            winner,
+        })
+    }
+
+    //Works out which neighbours a piece of the given size placed at coordinate would push. On a
+    //toroidal board a piece pushed over an edge comes back on the opposite one
+    fn boops(&self, coordinate: Coordinate, size: Size) -> Vec<Boop> {
+        let dirs = [
+            (-1, -1),
+            (-1, 0),
+            (-1, 1),
+            (0, 1),
+            (1, 1),
+            (1, 0),
+            (1, -1),
+            (0, -1),
+        ];
+
+        let mut boops: Vec<Boop> = Vec::new();
+        for (dx, dy) in dirs {
+            //the padding is wide enough that neither can leave the game board
+            let from = self.rules.offset(coordinate, dx, dy).unwrap();
+            let to = self.rules.offset(coordinate, dx * 2, dy * 2).unwrap();
+            match &self.game_board[from.x][from.y] {
+                Cell::Piece(piece)
+                    if piece.size == Size::Big
+                        && (size == Size::Small || !self.rules.cats_boop_cats) =>
+                {
+                    continue
+                }
+                Cell::Piece(_) => match self.game_board[to.x][to.y] {
+                    Cell::Piece(_) => continue,
+                    //on a toroidal board 4 cells across, pushes either side wrap round onto the
+                    //same cell. The boops happen in turn, so the first one blocks the second
+                    Cell::Empty if boops.iter().any(|boop| boop.to == Some(to)) => continue,
+                    Cell::OutOfBounds => boops.push(Boop { from, to: None }),
+                    Cell::Empty => boops.push(Boop { from, to: Some(to) }),
+                },
+                _ => continue,
+            }
+        }
+        boops
+    }
+
+    fn place_piece(
+        &mut self,
+        piece_placement: PiecePlacement,
+    ) -> Result<Vec<MoveEvent>, PlacePieceError> {
+        let player_index = self.turn_count % self.turn_order.len();
+
+        if piece_placement.coordinate.x >= self.rules.width {
             return Result::Err(PlacePieceError {
                 kind: PieceErrorKind::OutOfBoundsX,
             });
         }
-        if coordinate.y > 7 || coordinate.y < 2 {
+        if piece_placement.coordinate.y >= self.rules.height {
             return Result::Err(PlacePieceError {
                 kind: PieceErrorKind::OutOfBoundsY,
             });
         }
+        let coordinate = piece_placement.coordinate.padded();
 
         if self.game_board[coordinate.x][coordinate.y] != Cell::Empty {
             return Result::Err(PlacePieceError {
@@ -477,10 +1166,9 @@ impl GameState {
             });
         }
 
-        self.turn_order[player_index].piece_pool.remove(
-            self.turn_order[player_index]
-                .piece_pool
-                .iter()
+        let pool = &mut self.turn_order[player_index].piece_pool;
+        pool.remove(
+            pool.iter()
                 .position(|x| {
                     *x == Piece {
                         owner: player_index,
@@ -490,188 +1178,2351 @@ impl GameState {
                 .expect("needle not found"),
         );
 
-        self.game_board[coordinate.x][coordinate.y] = Cell::Piece(Piece {
+        let piece = Piece {
             owner: (player_index),
             size: (piece_placement.size),
-        });
+        };
+        self.game_board[coordinate.x][coordinate.y] = Cell::Piece(piece.clone());
+        let mut events = vec![MoveEvent::Placed {
+            piece,
+            at: piece_placement.coordinate,
+        }];
 
         //Bounce Adjacent Pieces
-        let dirs = [
-            Coordinate { x: 0, y: 0 },
-            Coordinate { x: 0, y: 1 },
-            Coordinate { x: 0, y: 2 },
-            Coordinate { x: 1, y: 2 },
-            Coordinate { x: 2, y: 2 },
-            Coordinate { x: 2, y: 1 },
-            Coordinate { x: 2, y: 0 },
-            Coordinate { x: 1, y: 0 },
-        ];
-
-        for d in dirs {
-            match &self.game_board[coordinate.x - 1 + d.x][coordinate.y - 1 + d.y] {
-                Cell::Piece(piece)
-                    if piece.size == Size::Big && piece_placement.size == Size::Small =>
-                {
-                    continue
+        for boop in self.boops(coordinate, piece_placement.size) {
+            let cell =
+                std::mem::replace(&mut self.game_board[boop.from.x][boop.from.y], Cell::Empty);
+            match boop.to {
+                Some(to) => {
+                    self.game_board[to.x][to.y] = cell;
+                    events.push(MoveEvent::Booped {
+                        from: boop.from.board_coordinate(),
+                        to: to.board_coordinate(),
+                    });
                 }
-                Cell::Piece(piece) => {
-                    match self.game_board[coordinate.x - 2 + d.x * 2][coordinate.y - 2 + d.y * 2] {
-                        Cell::Piece(_) => continue,
-                        Cell::OutOfBounds => {
+                None => {
+                    if let Cell::Piece(piece) = cell {
+                        if self.rules.booped_off_to_pool {
                             self.turn_order[piece.owner].piece_pool.push(piece.clone());
-                            self.game_board[coordinate.x - 1 + d.x][coordinate.y - 1 + d.y] =
-                                Cell::Empty;
-                        }
-                        Cell::Empty => {
-                            self.game_board[coordinate.x - 2 + d.x * 2]
-                                [coordinate.y - 2 + d.y * 2] = self.game_board
-                                [coordinate.x - 1 + d.x][coordinate.y - 1 + d.y]
-                                .clone();
-
-                            self.game_board[coordinate.x - 1 + d.x][coordinate.y - 1 + d.y] =
-                                Cell::Empty;
                         }
+                        events.push(MoveEvent::BoopedOff {
+                            piece,
+                            from: boop.from.board_coordinate(),
+                        });
                     }
                 }
-                _ => continue,
             }
         }
-        Result::Ok(())
+        self.last_events = events.clone();
+        Result::Ok(events)
     }
-}
 
-#[derive(Debug, PartialEq, Clone, Copy)]
-struct Coordinate {
-    x: usize,
-    y: usize,
-}
-impl std::str::FromStr for Coordinate {
-    type Err = ParseCoordinateError;
+    fn to_move(&self) -> usize {
+        self.turn_count % self.turn_order.len()
+    }
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (x, y) = s.split_once(',').ok_or(ParseCoordinateError {
-            kind: CoordinateErrorKind::InvalidFormat,
-        })?;
-        let x_fromstr = match x.parse::<usize>() {
-            Ok(x) => x,
-            Err(error) => {
-                return Err(ParseCoordinateError {
-                    kind: CoordinateErrorKind::ValueErrorX(error),
-                })
+    //Whether the player to move has any move. A player can run out of pieces to place once they
+    //are all on the board, or for good when booped off pieces don't come back to the pool
+    fn can_move(&self) -> bool {
+        let has_pieces = !self.turn_order[self.to_move()].piece_pool.is_empty();
+        let has_room = self
+            .rules
+            .board_cells()
+            .any(|c| self.game_board[c.x][c.y] == Cell::Empty);
+        self.winner.is_none() && ((has_pieces && has_room) || self.can_swap())
+    }
+
+    //Whether the game is stuck with no player able to move, which ends it in a draw
+    fn nobody_can_move(&self) -> bool {
+        (0..self.turn_order.len()).all(|turn| {
+            let mut later = self.clone();
+            later.turn_count += turn;
+            !later.can_move()
+        })
+    }
+
+    //Hands the turn on past players with nothing they can place, unless nobody can move at all
+    //and the game is a draw. Returns a Passed event for every turn sat out
+    fn pass_stuck_players(&mut self) -> Vec<MoveEvent> {
+        let mut events = Vec::new();
+        if self.nobody_can_move() {
+            return events;
+        }
+        while !self.can_move() {
+            events.push(MoveEvent::Passed {
+                player: self.to_move(),
+            });
+            self.turn_count += 1;
+        }
+        self.last_events.extend(events.iter().cloned());
+        events
+    }
+
+    //Whether the swap rule lets Player 2 swap now: it is their first turn and Player 1's
+    //opening piece is the only one on the board
+    fn can_swap(&self) -> bool {
+        let mut owners =
+            self.rules
+                .board_cells()
+                .filter_map(|c| match &self.game_board[c.x][c.y] {
+                    Cell::Piece(piece) => Some(piece.owner),
+                    _ => None,
+                });
+        self.rules.swap_rule
+            && self.winner.is_none()
+            && self.turn_count == 1
+            && owners.next() == Some(0)
+            && owners.next().is_none()
+    }
+
+    //Swaps the two sides' pieces over, so the opening piece becomes Player 2's and Player 1 is
+    //left with a full pool to move again. Names and clocks stay with the players
+    fn swap(&mut self) -> Vec<MoveEvent> {
+        let rules = self.rules;
+        for c in rules.board_cells() {
+            if let Cell::Piece(piece) = &mut self.game_board[c.x][c.y] {
+                piece.owner = 1 - piece.owner;
+            }
+        }
+        let (first, second) = self.turn_order.split_at_mut(1);
+        std::mem::swap(&mut first[0].piece_pool, &mut second[0].piece_pool);
+        for (owner, player) in self.turn_order.iter_mut().enumerate() {
+            for piece in player.piece_pool.iter_mut() {
+                piece.owner = owner;
+            }
+        }
+        self.last_events = vec![MoveEvent::Swapped];
+        self.last_events.clone()
+    }
+
+    //Plays a whole turn without asking anyone anything: places the piece, graduates the line
+    //chosen by the move and hands the turn over, past any player left with nothing to place. The
+    //state is untouched if the move is illegal
+    fn play(&mut self, player_move: &Move) -> Result<Vec<MoveEvent>, PlacePieceError> {
+        if self.winner.is_some() || (!self.can_move() && self.nobody_can_move()) {
+            return Result::Err(PlacePieceError {
+                kind: PieceErrorKind::GameOver,
+            });
+        }
+        let (placement, line) = match player_move {
+            Move::Place { placement, line } => (placement, *line),
+            Move::Swap => {
+                if !self.can_swap() {
+                    return Result::Err(PlacePieceError {
+                        kind: PieceErrorKind::SwapNotAllowed,
+                    });
+                }
+                let events = self.swap();
+                self.turn_count += 1;
+                return Result::Ok(events);
             }
         };
-        let y_fromstr = match y.parse::<usize>() {
-            Ok(y) => y,
-            Err(error) => {
-                return Err(ParseCoordinateError {
-                    kind: CoordinateErrorKind::ValueErrorY(error),
+        let mut next = self.clone();
+        let mut events = next.place_piece(placement.clone())?;
+
+        match next.check_board() {
+            BoardCheck::Resolved(graduation_events) => events.extend(graduation_events),
+            BoardCheck::Ambiguous(lines) => {
+                let line = line.ok_or(PlacePieceError {
+                    kind: PieceErrorKind::MissingLineChoice,
+                })?;
+                let line = line.padded();
+                let line = lines
+                    .iter()
+                    .find(|candidate| candidate.contains(line))
+                    .ok_or(PlacePieceError {
+                        kind: PieceErrorKind::InvalidLineChoice,
+                    })?;
+                let graduation_events = next.graduate(line);
+                next.last_events.extend(graduation_events.iter().cloned());
+                events.extend(graduation_events);
+            }
+        }
+
+        if next.winner.is_none() {
+            next.turn_count += 1;
+            events.extend(next.pass_stuck_players());
+        }
+        *self = next;
+        Result::Ok(events)
+    }
+
+    //Every move the player to move can make, with one move per line of three they could choose
+    fn legal_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::new();
+        if self.winner.is_some() {
+            return moves;
+        }
+
+        let pool = &self.turn_order[self.to_move()].piece_pool;
+        let sizes: Vec<Size> = [Size::Small, Size::Big]
+            .into_iter()
+            .filter(|size| pool.iter().any(|piece| piece.size == *size))
+            .collect();
+
+        for coordinate in self.rules.board_cells() {
+            if self.game_board[coordinate.x][coordinate.y] != Cell::Empty {
+                continue;
+            }
+            for size in &sizes {
+                moves.extend(self.placement_moves(PiecePlacement {
+                    coordinate: coordinate.board_coordinate(),
+                    size: *size,
+                }));
+            }
+        }
+        if self.can_swap() {
+            moves.push(Move::Swap);
+        }
+        moves
+    }
+
+    //The moves a placement makes, one for every line of three it could graduate. None when the
+    //placement is illegal
+    fn placement_moves(&self, placement: PiecePlacement) -> Vec<Move> {
+        let mut after = self.clone();
+        if after.place_piece(placement.clone()).is_err() {
+            return Vec::new();
+        }
+        match after.check_board() {
+            BoardCheck::Resolved(_) => vec![Move::Place {
+                placement,
+                line: None,
+            }],
+            BoardCheck::Ambiguous(lines) => lines
+                .iter()
+                .enumerate()
+                .map(|(index, line)| {
+                    //prefer a piece no earlier line contains so play picks this line
+                    let choice = line
+                        .0
+                        .iter()
+                        .copied()
+                        .find(|c| !lines[..index].iter().any(|l| l.contains(*c)))
+                        .unwrap_or(line.0[0]);
+                    Move::Place {
+                        placement: placement.clone(),
+                        line: Some(choice.board_coordinate()),
+                    }
                 })
+                .collect(),
+        }
+    }
+
+    //Checks the state could come out of a real game, for states built from outside input
+    fn validate(&self) -> Result<(), InvalidGameStateError> {
+        let players = self.turn_order.len();
+        let mut piece_counts = vec![0; players];
+
+        self.rules.check().map_err(|error| InvalidGameStateError {
+            kind: GameStateErrorKind::InvalidRules(error),
+        })?;
+        if players != self.rules.players {
+            return Err(InvalidGameStateError {
+                kind: GameStateErrorKind::WrongPlayerCount,
+            });
+        }
+        if self.game_board.len() != self.rules.width + 2 * PADDING
+            || self
+                .game_board
+                .iter()
+                .any(|column| column.len() != self.rules.height + 2 * PADDING)
+        {
+            return Err(InvalidGameStateError {
+                kind: GameStateErrorKind::MissingPadding,
+            });
+        }
+        for (x, column) in self.game_board.iter().enumerate() {
+            for (y, cell) in column.iter().enumerate() {
+                let on_board = self.rules.on_board(Coordinate { x, y });
+                match cell {
+                    Cell::OutOfBounds if on_board => {
+                        return Err(InvalidGameStateError {
+                            kind: GameStateErrorKind::OutOfBoundsOnBoard,
+                        })
+                    }
+                    Cell::OutOfBounds => (),
+                    _ if !on_board => {
+                        return Err(InvalidGameStateError {
+                            kind: GameStateErrorKind::MissingPadding,
+                        })
+                    }
+                    Cell::Empty => (),
+                    Cell::Piece(piece) => {
+                        if piece.owner >= players {
+                            return Err(InvalidGameStateError {
+                                kind: GameStateErrorKind::UnknownOwner,
+                            });
+                        }
+                        piece_counts[piece.owner] += 1;
+                    }
+                }
             }
-        };
+        }
 
-        Ok(Coordinate {
-            x: x_fromstr,
-            y: y_fromstr,
-        })
+        for (owner, player) in self.turn_order.iter().enumerate() {
+            if player.piece_pool.iter().any(|piece| piece.owner != owner) {
+                return Err(InvalidGameStateError {
+                    kind: GameStateErrorKind::PoolOwnerMismatch,
+                });
+            }
+            //pieces booped off the board can be gone for good
+            let pieces = piece_counts[owner] + player.piece_pool.len();
+            if pieces > self.rules.pieces_per_player
+                || (self.rules.booped_off_to_pool && pieces != self.rules.pieces_per_player)
+            {
+                return Err(InvalidGameStateError {
+                    kind: GameStateErrorKind::WrongPieceCount,
+                });
+            }
+            if self.cats_owned(owner) > self.rules.cat_cap {
+                return Err(InvalidGameStateError {
+                    kind: GameStateErrorKind::TooManyCats,
+                });
+            }
+        }
+
+        if self.winner.is_some_and(|winner| winner >= players) {
+            return Err(InvalidGameStateError {
+                kind: GameStateErrorKind::UnknownWinner,
+            });
+        }
+        //lines graduate before the turn ends, so no position between moves has one
+        if !self.lines().is_empty() {
+            return Err(InvalidGameStateError {
+                kind: GameStateErrorKind::LineOnBoard,
+            });
+        }
+        Ok(())
+    }
+
+    //Compact text form of the position: the board rows from y=0 down, the side to move and
+    //each player's pool, e.g. "....../..x.../....../....../....../...... o xxxxxxx oooooooo"
+    fn notation(&self) -> String {
+        let mut rows = Vec::new();
+        for y in PADDING..PADDING + self.rules.height {
+            let mut row = String::new();
+            for x in PADDING..PADDING + self.rules.width {
+                match &self.game_board[x][y] {
+                    Cell::Piece(piece) => row.push(piece.glyph()),
+                    _ => row.push('.'),
+                }
+            }
+            rows.push(row);
+        }
+
+        let mut notation = rows.join("/");
+        notation += " ";
+        notation.push(
+            Piece {
+                owner: self.to_move(),
+                size: Size::Small,
+            }
+            .glyph(),
+        );
+        for player in &self.turn_order {
+            let pool: String = player.piece_pool.iter().map(Piece::glyph).collect();
+            notation += " ";
+            notation += if pool.is_empty() { "-" } else { &pool };
+        }
+        notation
     }
 }
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct UncheckedGameState {
+    rules: RuleSet,
+    game_board: Vec<Vec<Cell>>,
+    turn_order: Vec<Player>,
+    turn_count: usize,
+    last_events: Vec<MoveEvent>,
+    winner: Option<usize>,
+}
+#[cfg(feature = "serde")]
+impl TryFrom<UncheckedGameState> for GameState {
+    type Error = InvalidGameStateError;
 
-#[derive(Debug)]
-struct PiecePlacement {
-    coordinate: Coordinate,
-    size: Size,
+    fn try_from(unchecked: UncheckedGameState) -> Result<Self, Self::Error> {
+        let game_state = GameState {
+            rules: unchecked.rules,
+            game_board: unchecked.game_board,
+            turn_order: unchecked.turn_order,
+            turn_count: unchecked.turn_count,
+            last_events: unchecked.last_events,
+            winner: unchecked.winner,
+        };
+        game_state.validate()?;
+        Ok(game_state)
+    }
 }
-impl std::str::FromStr for PiecePlacement {
-    type Err = ParsePiecePlacementError;
+//Positions in the standard rules
+impl std::str::FromStr for GameState {
+    type Err = ParseNotationError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (size, coordinate) = s.split_once(",").ok_or(ParsePiecePlacementError {
-            kind: PiecePlacementErrorKind::InvalidFormat,
-        })?;
-        let size_fromstr = match size.parse::<Size>() {
-            Ok(size) => size,
-            Err(error) => {
-                return Err(ParsePiecePlacementError {
-                    kind: PiecePlacementErrorKind::ValueErrorSize(error),
-                })
-            }
+        GameState::from_notation(s, RuleSet::default())
+    }
+}
+impl GameState {
+    //Reads a position written by notation, for a game played under the given rules
+    fn from_notation(s: &str, rules: RuleSet) -> Result<GameState, ParseNotationError> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        if fields.is_empty() {
+            return Err(ParseNotationError {
+                kind: NotationErrorKind::Empty,
+            });
+        }
+        //the board, the side to move and one pool for every player
+        let [board, side_to_move, ref pools @ ..] = fields[..] else {
+            return Err(ParseNotationError {
+                kind: NotationErrorKind::InvalidFormat,
+            });
         };
-        let coordinate_fromstr = match coordinate.parse::<Coordinate>() {
-            Ok(coordinate) => coordinate,
-            Err(error) => {
-                return Err(ParsePiecePlacementError {
-                    kind: PiecePlacementErrorKind::ValueErrorCoordinate(error),
+        if pools.len() != rules.players {
+            return Err(ParseNotationError {
+                kind: NotationErrorKind::InvalidFormat,
+            });
+        }
+
+        let mut game_state = GameState::with_rules(rules);
+        for player in game_state.turn_order.iter_mut() {
+            player.piece_pool.clear();
+        }
+
+        let rows: Vec<&str> = board.split('/').collect();
+        if rows.len() != rules.height {
+            return Err(ParseNotationError {
+                kind: NotationErrorKind::InvalidBoard,
+            });
+        }
+        for (y, row) in rows.iter().enumerate() {
+            if row.chars().count() != rules.width {
+                return Err(ParseNotationError {
+                    kind: NotationErrorKind::InvalidBoard,
+                });
+            }
+            for (x, glyph) in row.chars().enumerate() {
+                game_state.game_board[x + PADDING][y + PADDING] = match glyph {
+                    '.' => Cell::Empty,
+                    _ => Cell::Piece(Piece::from_glyph(glyph).ok_or(ParseNotationError {
+                        kind: NotationErrorKind::InvalidBoard,
+                    })?),
+                };
+            }
+        }
+
+        let mut side_to_move = side_to_move.chars();
+        game_state.turn_count = match (
+            side_to_move.next().and_then(Piece::from_glyph),
+            side_to_move.next(),
+        ) {
+            (
+                Some(Piece {
+                    owner,
+                    size: Size::Small,
+                }),
+                None,
+            ) if owner < rules.players => owner,
+            _ => {
+                return Err(ParseNotationError {
+                    kind: NotationErrorKind::InvalidSideToMove,
                 })
             }
         };
 
-        Ok(PiecePlacement {
-            coordinate: coordinate_fromstr,
-            size: size_fromstr,
-        })
+        for (owner, pool) in pools.iter().enumerate() {
+            if *pool == "-" {
+                continue;
+            }
+            for glyph in pool.chars() {
+                match Piece::from_glyph(glyph) {
+                    Some(piece) if piece.owner == owner => {
+                        game_state.turn_order[owner].piece_pool.push(piece)
+                    }
+                    _ => {
+                        return Err(ParseNotationError {
+                            kind: NotationErrorKind::InvalidPool,
+                        })
+                    }
+                }
+            }
+        }
+
+        game_state.validate().map_err(|error| ParseNotationError {
+            kind: NotationErrorKind::ImpossiblePosition(error),
+        })?;
+        Ok(game_state)
     }
 }
-impl PiecePlacement {
-    fn ask_player(player_name: &str) -> Result<PiecePlacement, ParsePiecePlacementError> {
-        let mut input = String::new();
-
-        println!("{} to move:", player_name);
-        std::io::stdin()
-            .read_line(&mut input)
-            .expect("Failed to read line");
 
-        let piece_placement = input.trim().parse()?;
+const DEFAULT_PORT: u16 = 7878;
+const DEFAULT_HTTP_PORT: u16 = 8080;
 
-        Result::Ok(piece_placement)
+//The number after --port, default when there is none and None when it is not a port
+fn port_arg(args: &[String], default: u16) -> Option<u16> {
+    match args.iter().position(|arg| arg == "--port") {
+        Some(index) => args.get(index + 1)?.parse().ok(),
+        None => Some(default),
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
-struct ThreeInRow(Coordinate, Coordinate, Coordinate);
+//Rules for a local game: house rules from a file with --rules, otherwise the standard game or a
+//bigger table with --players N. --pieces N changes how many pieces everyone starts with,
+//--toroidal wraps the board around and --swap plays with the swap rule
+fn rules_arg(args: &[String]) -> Result<RuleSet, BoopError> {
+    let missing_value = |flag: &str, usage| ArgumentError {
+        kind: ArgumentErrorKind::MissingValue {
+            flag: flag.to_string(),
+            usage,
+        },
+    };
+    if let Some(index) = args.iter().position(|arg| arg == "--rules") {
+        let path = args
+            .get(index + 1)
+            .ok_or(missing_value("--rules", "<file>"))?;
+        if args
+            .iter()
+            .any(|arg| ["--players", "--pieces", "--toroidal", "--swap"].contains(&arg.as_str()))
+        {
+            return Err(ArgumentError {
+                kind: ArgumentErrorKind::RulesFileConflict,
+            }
+            .into());
+        }
+        return load_rules(path);
+    }
 
-fn win(winner: &str) {
-    println!("{winner} wins!")
-}
-fn main() {
-    let mut game_state = GameState::init();
-    game_state.display();
+    let number_after = |flag: &str| match args.iter().position(|arg| arg == flag) {
+        Some(index) => args
+            .get(index + 1)
+            .and_then(|value| value.parse::<usize>().ok())
+            .map(Some)
+            .ok_or(missing_value(flag, "N")),
+        None => Ok(None),
+    };
 
-    loop {
-        let player_move = match PiecePlacement::ask_player(
-            &game_state.turn_order[game_state.turn_count % 2].name,
-        ) {
-            Ok(ok) => ok,
-            Err(ParsePiecePlacementError { kind }) => {
-                match kind {
-                    PiecePlacementErrorKind::Empty => panic!(),
-                    PiecePlacementErrorKind::InvalidFormat => {
-                        println!("Invalid format: should be \"size,x,y\"")
-                    }
-                    PiecePlacementErrorKind::ValueErrorSize(_) => {
-                        println!("Invalid Size");
-                    }
-                    PiecePlacementErrorKind::ValueErrorCoordinate(_) => {
-                        println!("Invalid Coordinate");
-                    }
-                };
-                continue;
-            }
-        };
-        match game_state.place_piece(player_move) {
-            Ok(_) => (),
-            Err(PlacePieceError { kind }) => {
-                match kind {
-                    PieceErrorKind::OutOfBoundsX => println!("Out of Bounds"),
-                    PieceErrorKind::OutOfBoundsY => println!("Out of Bounds"),
-                    PieceErrorKind::PositionOccupied => println!("Occupied Position"),
-                    PieceErrorKind::MissingPiece => println!("Piece Unavailable"),
-                }
-                continue;
+    let mut rules = match number_after("--players")? {
+        Some(players) if (2..=MAX_PLAYERS).contains(&players) => RuleSet::for_players(players),
+        Some(_) => {
+            return Err(ArgumentError {
+                kind: ArgumentErrorKind::PlayerCount,
             }
+            .into())
+        }
+        None => RuleSet::default(),
+    };
+    if let Some(pieces) = number_after("--pieces")? {
+        rules.pieces_per_player = pieces;
+        rules.cat_cap = pieces;
+    }
+    rules.toroidal = args.iter().any(|arg| arg == "--toroidal");
+    rules.swap_rule = args.iter().any(|arg| arg == "--swap");
+    rules.check()?;
+    Ok(rules)
+}
+
+//Reads house rules, as JSON when the file name ends in .json and TOML otherwise
+fn load_rules(path: &str) -> Result<RuleSet, BoopError> {
+    let text = read_file(path)?;
+    let rules = if path.ends_with(".json") {
+        rules::from_json(&text)
+    } else {
+        rules::from_toml(&text)
+    };
+    rules.map_err(|error| {
+        LoadFileError {
+            path: path.to_string(),
+            kind: LoadFileErrorKind::Rules(error),
         }
-        game_state.check_board(None);
-        game_state.turn_count += 1;
+        .into()
+    })
+}
+
+//The position a local game starts from: a saved game or setup file with --load, a position in
+//notation with --position, or a new game with any --handicap presets, separated by commas
+fn start_position(args: &[String], rules: RuleSet) -> Result<GameState, BoopError> {
+    let value_after = |flag: &str, usage| match args.iter().position(|arg| arg == flag) {
+        Some(index) => args.get(index + 1).map(Some).ok_or(ArgumentError {
+            kind: ArgumentErrorKind::MissingValue {
+                flag: flag.to_string(),
+                usage,
+            },
+        }),
+        None => Ok(None),
+    };
+    let load = value_after("--load", "<file>")?;
+    let position = value_after("--position", "<notation>")?;
+    let handicaps = value_after("--handicap", "cat|two-cats|first-move[,...]")?;
+
+    match (load, position, handicaps) {
+        (Some(path), None, None) => load_game(path, rules),
+        (None, Some(notation), None) => {
+            GameState::from_notation(notation, rules).map_err(|error| {
+                ArgumentError {
+                    kind: ArgumentErrorKind::InvalidPosition(error),
+                }
+                .into()
+            })
+        }
+        (None, None, handicaps) => {
+            let mut game_state = GameState::with_rules(rules);
+            for handicap in handicaps
+                .into_iter()
+                .flat_map(|handicaps| handicaps.split(','))
+            {
+                handicap.parse::<Handicap>()?.apply(&mut game_state);
+            }
+            game_state.validate().map_err(|error| ArgumentError {
+                kind: ArgumentErrorKind::InvalidHandicap(error),
+            })?;
+            Ok(game_state)
+        }
+        _ => Err(ArgumentError {
+            kind: ArgumentErrorKind::SeveralStarts,
+        }
+        .into()),
+    }
+}
+
+//ANSI colour codes for each player's pieces, indexed by owner
+const PLAYER_COLORS: [&str; MAX_PLAYERS] = ["31", "34", "32", "33"];
+
+fn paint(text: &str, codes: &[&str]) -> String {
+    format!("\x1b[{}m{}\x1b[0m", codes.join(";"), text)
+}
+
+//Decides whether the terminal output should use ANSI colours
+fn use_color(args: &[String]) -> bool {
+    if args.iter().any(|arg| arg == "--no-color") {
+        return false;
+    }
+    if std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+        return false;
+    }
+    std::io::stdout().is_terminal()
+}
+
+//The alternate flag ("{:#}") renders with ANSI colours
+impl fmt::Display for GameState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let color = f.alternate();
+        let to_move = self.turn_count % self.turn_order.len();
+        let piece_glyph = |piece: &Piece, coordinate: Option<Coordinate>| {
+            let glyph = piece.glyph().to_string();
+            if !color {
+                return glyph;
+            }
+            let mut codes = vec![PLAYER_COLORS[piece.owner]];
+            if piece.size == Size::Big {
+                codes.push("1");
+            }
+            if let Some(coordinate) = coordinate.map(Coordinate::board_coordinate) {
+                let placed_here = self.last_events.iter().any(
+                    |event| matches!(event, MoveEvent::Placed { at, .. } if *at == coordinate),
+                );
+                let booped_here = self.last_events.iter().any(
+                    |event| matches!(event, MoveEvent::Booped { to, .. } if *to == coordinate),
+                );
+                if placed_here {
+                    codes.push("7");
+                } else if booped_here {
+                    codes.push("4");
+                }
+            }
+            paint(&glyph, &codes)
+        };
+        let pool_line = |player_index: usize| {
+            let player = &self.turn_order[player_index];
+            let (kittens, cats) = player.pool_counts();
+            let marker = if player_index == to_move { ">" } else { " " };
+            let mut label = format!("{} {}", marker, player.name);
+            if color && player_index == to_move {
+                label = paint(&label, &["1"]);
+            }
+            format!(
+                "{} ({}) kittens: {}, cats: {}",
+                label,
+                piece_glyph(
+                    &Piece {
+                        owner: player_index,
+                        size: Size::Small,
+                    },
+                    None
+                ),
+                kittens,
+                cats
+            )
+        };
+
+        writeln!(f, "{}", pool_line(0))?;
+
+        //columns are as wide as the widest x label so they stay lined up past x=9
+        let column_width = self.rules.width.saturating_sub(1).to_string().len();
+        let padding = " ".repeat(column_width);
+        write!(f, "y\\x")?;
+        for x in 0..self.rules.width {
+            write!(f, " {:>column_width$}", x)?;
+        }
+        writeln!(f)?;
+
+        for y in PADDING..PADDING + self.rules.height {
+            write!(f, "{:>3}", y - PADDING)?;
+            for x in PADDING..PADDING + self.rules.width {
+                match &self.game_board[x][y] {
+                    Cell::Empty => write!(f, "{}_", padding)?,
+                    Cell::Piece(piece) => write!(
+                        f,
+                        "{}{}",
+                        padding,
+                        piece_glyph(piece, Some(Coordinate { x, y }))
+                    )?,
+                    Cell::OutOfBounds => panic!("out of bounds cell inside board"),
+                }
+            }
+            writeln!(f)?;
+        }
+
+        //everyone after the first player is listed under the board
+        for player_index in 1..self.turn_order.len() {
+            write!(f, "{}", pool_line(player_index))?;
+            if player_index + 1 < self.turn_order.len() {
+                writeln!(f)?;
+            }
+        }
+        Result::Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Coordinate {
+    x: usize,
+    y: usize,
+}
+impl Coordinate {
+    //Converts a padded game board coordinate back to the "x,y" the players type
+    fn board_coordinate(self) -> Coordinate {
+        Coordinate {
+            x: self.x - PADDING,
+            y: self.y - PADDING,
+        }
+    }
+
+    //Converts the "x,y" the players type to a padded game board coordinate. Typed coordinates
+    //near usize::MAX saturate rather than overflow, which still leaves them off the board
+    fn padded(self) -> Coordinate {
+        Coordinate {
+            x: self.x.saturating_add(PADDING),
+            y: self.y.saturating_add(PADDING),
+        }
+    }
+}
+impl fmt::Display for Coordinate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{}", self.x, self.y)
+    }
+}
+impl std::str::FromStr for Coordinate {
+    type Err = ParseCoordinateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(ParseCoordinateError {
+                kind: CoordinateErrorKind::Empty,
+            });
+        }
+        let (x, y) = s.split_once(',').ok_or(ParseCoordinateError {
+            kind: CoordinateErrorKind::InvalidFormat,
+        })?;
+        let x_fromstr = match x.trim().parse::<usize>() {
+            Ok(x) => x,
+            Err(error) => {
+                return Err(ParseCoordinateError {
+                    kind: CoordinateErrorKind::ValueErrorX(error),
+                })
+            }
+        };
+        let y_fromstr = match y.trim().parse::<usize>() {
+            Ok(y) => y,
+            Err(error) => {
+                return Err(ParseCoordinateError {
+                    kind: CoordinateErrorKind::ValueErrorY(error),
+                })
+            }
+        };
+
+        Ok(Coordinate {
+            x: x_fromstr,
+            y: y_fromstr,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct PiecePlacement {
+    coordinate: Coordinate,
+    size: Size,
+}
+impl fmt::Display for PiecePlacement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{}", self.size, self.coordinate)
+    }
+}
+impl std::str::FromStr for PiecePlacement {
+    type Err = ParsePiecePlacementError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(ParsePiecePlacementError {
+                kind: PiecePlacementErrorKind::Empty,
+            });
+        }
+        let (size, coordinate) = s.split_once(",").ok_or(ParsePiecePlacementError {
+            kind: PiecePlacementErrorKind::InvalidFormat,
+        })?;
+        let size_fromstr = match size.parse::<Size>() {
+            Ok(size) => size,
+            Err(error) => {
+                return Err(ParsePiecePlacementError {
+                    kind: PiecePlacementErrorKind::ValueErrorSize(error),
+                })
+            }
+        };
+        let coordinate_fromstr = match coordinate.parse::<Coordinate>() {
+            Ok(coordinate) => coordinate,
+            Err(error) => {
+                return Err(ParsePiecePlacementError {
+                    kind: PiecePlacementErrorKind::ValueErrorCoordinate(error),
+                })
+            }
+        };
+
+        Ok(PiecePlacement {
+            coordinate: coordinate_fromstr,
+            size: size_fromstr,
+        })
+    }
+}
+
+//A complete turn: the placement plus, when it forms several lines of three, a piece of the line
+//that graduates. Written "size,x,y" or "size,x,y:x,y", or "swap" for taking over the opening
+//piece under the swap rule
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum Move {
+    Place {
+        placement: PiecePlacement,
+        line: Option<Coordinate>,
+    },
+    Swap,
+}
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Move::Place {
+                placement,
+                line: Some(line),
+            } => write!(f, "{}:{}", placement, line),
+            Move::Place {
+                placement,
+                line: None,
+            } => placement.fmt(f),
+            Move::Swap => write!(f, "swap"),
+        }
+    }
+}
+impl std::str::FromStr for Move {
+    type Err = ParsePiecePlacementError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim() == "swap" {
+            return Ok(Move::Swap);
+        }
+        let (placement, line) = match s.split_once(':') {
+            Some((placement, line)) => (placement, Some(line)),
+            None => (s, None),
+        };
+        let line = match line.map(str::parse::<Coordinate>) {
+            None => None,
+            Some(Ok(line)) => Some(line),
+            Some(Err(error)) => {
+                return Err(ParsePiecePlacementError {
+                    kind: PiecePlacementErrorKind::ValueErrorCoordinate(error),
+                })
+            }
+        };
+
+        Ok(Move::Place {
+            placement: placement.parse()?,
+            line,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Place(PiecePlacement),
+    Preview(PiecePlacement),
+    Swap,
+    Hint(usize),
+    Resign,
+    OfferDraw,
+    Accept,
+    Takeback,
+    Help,
+    History,
+    Quit,
+}
+//Anything that is not one of the command words is read as a placement
+impl std::str::FromStr for Command {
+    type Err = ParsePiecePlacementError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(piece_placement) = s.strip_prefix("preview ") {
+            return Ok(Command::Preview(piece_placement.trim().parse()?));
+        }
+        let words: Vec<&str> = s.split_whitespace().collect();
+        if let ["hint", count] = words.as_slice() {
+            if let Result::Ok(count) = count.parse() {
+                return Ok(Command::Hint(count));
+            }
+        }
+        match words.as_slice() {
+            ["swap"] => Ok(Command::Swap),
+            ["hint"] => Ok(Command::Hint(DEFAULT_HINTS)),
+            ["resign"] => Ok(Command::Resign),
+            ["offer", "draw"] => Ok(Command::OfferDraw),
+            ["accept"] => Ok(Command::Accept),
+            ["takeback"] => Ok(Command::Takeback),
+            ["help"] => Ok(Command::Help),
+            ["history"] => Ok(Command::History),
+            ["quit"] => Ok(Command::Quit),
+            _ => Ok(Command::Place(s.parse()?)),
+        }
+    }
+}
+impl Command {
+    //None once there is no more input
+    fn ask_player(player_name: &str) -> Option<Result<Command, ParsePiecePlacementError>> {
+        println!("{} to move:", player_name);
+        read_input().map(|input| input.parse())
+    }
+}
+
+const HELP: &str = "Commands:
+  size,x,y            place a piece, size is small or big
+  preview size,x,y    show what a placement would do without playing it
+  swap                take over your opponent's opening piece under the swap rule
+  hint [N]            show the bot's N best moves, 3 when left out
+  resign              give up the game
+  offer draw          offer your opponent a draw
+  accept              accept a draw offer
+  takeback            take back the last move
+  history             list the moves played so far
+  help                show this list
+  quit                leave the game";
+
+//Numbered lines like "1. Player 1: s,2,3" for the moves played, each with the player who played it
+fn history_lines(game_state: &GameState, history: &[(usize, Move)]) -> Vec<String> {
+    history
+        .iter()
+        .enumerate()
+        .map(|(index, (player, player_move))| {
+            format!(
+                "{}. {}: {}",
+                index + 1,
+                game_state.turn_order[*player].name,
+                player_move
+            )
+        })
+        .collect()
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Line(Vec<Coordinate>);
+impl Line {
+    fn contains(&self, coordinate: Coordinate) -> bool {
+        self.0.contains(&coordinate)
+    }
+
+    //Whether both lines are made of the same cells, whichever one they start from
+    fn same_cells(&self, other: &Line) -> bool {
+        self.0.len() == other.0.len() && self.0.iter().all(|c| other.contains(*c))
+    }
+
+    //The same line in the "x,y" coordinates the players type
+    fn board_coordinates(&self) -> Line {
+        Line(self.0.iter().map(|c| c.board_coordinate()).collect())
+    }
+}
+
+//What a placement would do: pieces pushed, lines of three formed and who would win. Coordinates
+//are the "x,y" the players type
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Preview {
+    boops: Vec<Boop>,
+    lines: Vec<Line>,
+    winner: Option<usize>,
+}
+
+//Everything that happens to the board during a move, in the order it happens.
+//Coordinates are the "x,y" the players type
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum MoveEvent {
+    Placed { piece: Piece, at: Coordinate },
+    Booped { from: Coordinate, to: Coordinate },
+    BoopedOff { piece: Piece, from: Coordinate },
+    LineFormed(Line),
+    Graduated { owner: usize },
+    Won { winner: usize },
+    //the sides swapped pieces under the swap rule
+    Swapped,
+    //the player had nothing left to place and sat their turn out
+    Passed { player: usize },
+}
+
+//Outcome of looking for lines of three after a placement
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum BoardCheck {
+    Resolved(Vec<MoveEvent>),
+    Ambiguous(Vec<Line>),
+}
+
+//A piece pushed away by a placement, to is None when it is pushed off the board
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Boop {
+    from: Coordinate,
+    to: Option<Coordinate>,
+}
+impl Boop {
+    //The same boop in the "x,y" coordinates the players type
+    fn board_coordinates(self) -> Boop {
+        Boop {
+            from: self.from.board_coordinate(),
+            to: self.to.map(Coordinate::board_coordinate),
+        }
+    }
+}
+
+fn win(winner: &str) {
+    println!("{winner} wins!")
+}
+//One line typed by the player, None at the end of input (Ctrl-D) or when stdin cannot be read
+fn read_input() -> Option<String> {
+    let mut input = String::new();
+    match std::io::stdin().read_line(&mut input) {
+        Result::Ok(0) => None,
+        Result::Ok(_) => Some(input.trim().to_string()),
+        Result::Err(error) => {
+            eprintln!("cannot read input: {}", error);
+            None
+        }
+    }
+}
+
+//Board coordinate of the piece chosen, as the player typed it. None once there is no more input
+fn ask_constraining_coordinate() -> Option<Result<Coordinate, ParseCoordinateError>> {
+    println!("Select constraining Piece");
+    let input = read_input()?;
+    Some(input.parse::<Coordinate>())
+}
+
+fn print_passes(game_state: &GameState, events: &[MoveEvent]) {
+    for event in events {
+        if let MoveEvent::Passed { player } = event {
+            println!(
+                "{} has no pieces left to place and passes",
+                game_state.turn_order[*player].name
+            );
+        }
+    }
+}
+
+fn print_lines(lines: &[Line]) {
+    for line in lines {
+        let cells: Vec<String> = line.0.iter().map(Coordinate::to_string).collect();
+        println!("Line at {}", cells.join(" "));
+    }
+}
+
+fn print_preview(game_state: &GameState, preview: &Preview) {
+    if preview.boops.is_empty() {
+        println!("No pieces booped");
+    }
+    for boop in &preview.boops {
+        let from = boop.from.padded();
+        let Cell::Piece(piece) = &game_state.game_board[from.x][from.y] else {
+            continue;
+        };
+        match boop.to {
+            Some(to) => println!("{} at {} booped to {}", piece.glyph(), boop.from, to),
+            None if game_state.rules.booped_off_to_pool => println!(
+                "{} at {} booped off the board back to {}'s pool",
+                piece.glyph(),
+                boop.from,
+                game_state.turn_order[piece.owner].name
+            ),
+            None => println!(
+                "{} at {} booped off the board and out of the game",
+                piece.glyph(),
+                boop.from
+            ),
+        }
+    }
+    print_lines(&preview.lines);
+    if let Some(winner) = preview.winner {
+        println!("{} would win", game_state.turn_order[winner].name);
+    }
+}
+
+const ANALYSIS_DEPTH: u32 = 3;
+const DEFAULT_HINTS: usize = 3;
+
+//The count best moves with their scores and the lines the bot expects, along with a warning when
+//the player to move can win right away or loses whatever they do
+fn print_analysis(game_state: &GameState, depth: u32, count: usize) {
+    if !ai::supports(game_state) {
+        println!("The bot only analyzes two player games");
+        return;
+    }
+    let analysis = ai::analyze(game_state, depth);
+    let Some(best) = analysis.first() else {
+        println!("No legal moves");
+        return;
+    };
+    let mover = &game_state.turn_order[game_state.to_move()].name;
+    let in_moves = |moves: i32| match moves.abs() {
+        1 => String::from("in 1 move"),
+        moves => format!("in {} moves", moves),
+    };
+    match ai::mate_in(best.score) {
+        Some(1) => println!("{} can win right now with {}", mover, best.pv[0]),
+        Some(moves) if moves > 0 => println!("{} can force a win {}", mover, in_moves(moves)),
+        Some(moves) => println!(
+            "{} loses whatever they do, the other side can force a win {}",
+            mover,
+            in_moves(moves)
+        ),
+        None => (),
+    }
+    for (index, info) in analysis.iter().take(count).enumerate() {
+        let pv: Vec<String> = info.pv.iter().map(Move::to_string).collect();
+        println!(
+            "{}. {} ({}) {}",
+            index + 1,
+            info.pv[0],
+            score_text(info.score),
+            pv.join(" ")
+        );
+    }
+}
+
+//A search score for people: the points, or how many moves a forced win takes
+fn score_text(score: i32) -> String {
+    match ai::mate_in(score) {
+        Some(moves) if moves > 0 => format!("wins in {}", moves),
+        Some(moves) => format!("other side wins in {}", -moves),
+        None => format!("{:+}", score),
+    }
+}
+
+//Once a game is over the players can have every move judged, and save the annotated game record
+fn offer_review(previous_states: &[GameState], history: &[(usize, Move)], end: &GameState) {
+    println!("Type \"review\" to have every move judged, or \"review file\" to also save the annotated game to file");
+    let Some(input) = read_input() else {
+        return;
+    };
+    let path = match input.split_whitespace().collect::<Vec<&str>>().as_slice() {
+        ["review"] => None,
+        ["review", path] => Some(path.to_string()),
+        _ => return,
+    };
+    let record = review::run(previous_states, history, end);
+    if let Some(path) = path {
+        match std::fs::write(&path, record) {
+            Result::Ok(()) => println!("Annotated game saved to {}", path),
+            Result::Err(error) => eprintln!("cannot save annotated game to {}: {}", path, error),
+        }
+    }
+}
+
+//Reads a saved game or a setup file: a position in notation, where lines starting with "#" are
+//comments
+fn load_game(path: &str, rules: RuleSet) -> Result<GameState, BoopError> {
+    let contents = read_file(path)?;
+    let notation: Vec<&str> = contents
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .collect();
+    GameState::from_notation(&notation.join(" "), rules).map_err(|error| {
+        LoadFileError {
+            path: path.to_string(),
+            kind: LoadFileErrorKind::Game(error),
+        }
+        .into()
+    })
+}
+
+//Ctrl-D quits, saving the game first when an autosave file was given
+fn end_of_input(game_state: &GameState, autosave: Option<&String>) {
+    println!();
+    let Some(path) = autosave else {
+        return;
+    };
+    if game_state.winner.is_some() {
+        return;
+    }
+    match std::fs::write(path, game_state.notation() + "\n") {
+        Result::Ok(()) => println!("Game saved to {}, continue it with --load {}", path, path),
+        Result::Err(error) => eprintln!("cannot save game to {}: {}", path, error),
+    }
+}
+
+fn print_error(error: impl Into<BoopError>) {
+    println!("{}", error_chain(&error.into()));
+}
+
+fn print_clock(game_state: &GameState, clock: &Clock<impl TimeSource>) {
+    let times: Vec<String> = game_state
+        .turn_order
+        .iter()
+        .enumerate()
+        .map(|(index, player)| {
+            format!(
+                "{}: {}",
+                player.name,
+                clock::format_time(clock.remaining(index))
+            )
+        })
+        .collect();
+    println!("Clock - {}", times.join(", "));
+}
+
+//Running out of time loses the game whatever is on the board
+fn lose_on_time(game_state: &mut GameState, loser: usize, color: bool) {
+    println!("{} ran out of time", game_state.turn_order[loser].name);
+    game_state.winner = conceded_to(game_state, loser);
+    print_game_state(game_state, color);
+    print_conceded(game_state);
+}
+
+//Who wins when loser resigns or runs out of time: the other player of a two player game. With
+//more players nobody has earned the win, so the game ends without a winner
+fn conceded_to(game_state: &GameState, loser: usize) -> Option<usize> {
+    (game_state.turn_order.len() == 2).then_some(1 - loser)
+}
+
+fn print_conceded(game_state: &GameState) {
+    match game_state.winner {
+        Some(winner) => win(&game_state.turn_order[winner].name),
+        None => println!("The game ends without a winner"),
+    }
+}
+
+fn print_game_state(game_state: &GameState, color: bool) {
+    if color {
+        println!("{:#}", game_state);
+    } else {
+        println!("{}", game_state);
+    }
+}
+
+//"analyze <notation> [--depth N] [--top N]" prints the best moves of a position, which is played
+//under the rules given the same way as for a game
+fn analyze_position(args: &[String]) {
+    const USAGE: &str = "usage: boop_game analyze <notation> [--depth N] [--top N]";
+    let notation: Vec<&str> = args
+        .iter()
+        .skip(2)
+        .take_while(|arg| !arg.starts_with("--"))
+        .map(String::as_str)
+        .collect();
+    if notation.is_empty() {
+        eprintln!("{}", USAGE);
+        return;
+    }
+    let number = |name: &str, default| match args.iter().position(|arg| arg == name) {
+        None => Some(default),
+        Some(index) => args.get(index + 1)?.parse().ok(),
+    };
+    let (Some(depth), Some(count)) = (
+        number("--depth", ANALYSIS_DEPTH as usize),
+        number("--top", DEFAULT_HINTS),
+    ) else {
+        eprintln!("{}", USAGE);
+        return;
+    };
+    let game_state = match rules_arg(args).and_then(|rules| {
+        GameState::from_notation(&notation.join(" "), rules).map_err(|error| {
+            ArgumentError {
+                kind: ArgumentErrorKind::InvalidPosition(error),
+            }
+            .into()
+        })
+    }) {
+        Result::Ok(game_state) => game_state,
+        Result::Err(error) => {
+            eprintln!("{}", error_chain(&error));
+            return;
+        }
+    };
+    print_game_state(&game_state, use_color(args));
+    print_analysis(&game_state, depth as u32, count);
+}
+
+//"puzzle" lists the puzzles, "puzzle N" plays puzzle N and "puzzle --check" has the solver prove
+//every one of them. They are the built-in ones unless --file names a file in the puzzle format.
+//"puzzle --generate" finds new ones in self-play
+fn run_puzzles(args: &[String]) {
+    if args.iter().any(|arg| arg == "--generate") {
+        generate_puzzles(args);
+        return;
+    }
+    let mut path = None;
+    let mut choice = None;
+    let mut rest = args.iter().skip(2);
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--file" => path = rest.next(),
+            "--no-color" => (),
+            _ => choice = Some(arg.as_str()),
+        }
+    }
+    let puzzles = match path {
+        Some(path) => match load_puzzles(path) {
+            Result::Ok(puzzles) => puzzles,
+            Result::Err(error) => {
+                eprintln!("{}", error_chain(&error));
+                return;
+            }
+        },
+        None => puzzle::built_in(),
+    };
+
+    match choice {
+        None => {
+            for (index, puzzle) in puzzles.iter().enumerate() {
+                let mut details = format!("win in {}", puzzle.moves);
+                if let Some(difficulty) = puzzle.difficulty {
+                    details += &format!(", difficulty {}", difficulty);
+                }
+                if let Some(theme) = &puzzle.theme {
+                    details += &format!(", {}", theme);
+                }
+                println!("{}. {}: {}", index + 1, puzzle.name, details);
+            }
+            println!("play one with \"boop_game puzzle N\"");
+        }
+        Some("--check") => {
+            for (index, puzzle) in puzzles.iter().enumerate() {
+                match puzzle.check() {
+                    Result::Ok(()) => println!("{}. {}: ok", index + 1, puzzle.name),
+                    Result::Err(error) => {
+                        println!("{}. {}: {}", index + 1, puzzle.name, error_chain(&error))
+                    }
+                }
+            }
+        }
+        Some(number) => {
+            let puzzle = number
+                .parse::<usize>()
+                .ok()
+                .and_then(|number| puzzles.get(number.checked_sub(1)?));
+            let Some(puzzle) = puzzle else {
+                eprintln!(
+                    "usage: boop_game puzzle [--file file] [1-{}|--check]",
+                    puzzles.len()
+                );
+                return;
+            };
+            if let Err(error) = puzzle::run(puzzle, use_color(args)) {
+                eprintln!("{}", error_chain(&error));
+            }
+        }
+    }
+}
+
+fn load_puzzles(path: &str) -> Result<Vec<puzzle::Puzzle>, BoopError> {
+    let contents = read_file(path)?;
+    puzzle::parse_puzzles(&contents).map_err(|error| {
+        LoadFileError {
+            path: path.to_string(),
+            kind: LoadFileErrorKind::Puzzles(error),
+        }
+        .into()
+    })
+}
+
+//"puzzle --generate [--games N] [--moves N] [--seed S] [--out file]" plays N games (10 when left
+//out) and prints the puzzles found, or adds them to the end of file
+fn generate_puzzles(args: &[String]) {
+    const USAGE: &str =
+        "usage: boop_game puzzle --generate [--games N] [--moves 1-3] [--seed S] [--out file]";
+    let value = |name: &str| {
+        args.iter()
+            .position(|arg| arg == name)
+            .map(|index| args.get(index + 1))
+    };
+    let games = match value("--games") {
+        None => 10,
+        Some(games) => match games.and_then(|games| games.parse().ok()) {
+            Some(games) => games,
+            None => {
+                eprintln!("{}", USAGE);
+                return;
+            }
+        },
+    };
+    let max_moves = match value("--moves") {
+        None => generator::MAX_MOVES,
+        Some(moves) => match moves.and_then(|moves| moves.parse().ok()) {
+            Some(moves) if (1..=generator::MAX_MOVES).contains(&moves) => moves,
+            _ => {
+                eprintln!("{}", USAGE);
+                return;
+            }
+        },
+    };
+    let seed = match value("--seed") {
+        None => std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or_default(),
+        Some(seed) => match seed.and_then(|seed| seed.parse().ok()) {
+            Some(seed) => seed,
+            None => {
+                eprintln!("{}", USAGE);
+                return;
+            }
+        },
+    };
+    let mut out = match value("--out") {
+        None => None,
+        Some(None) => {
+            eprintln!("{}", USAGE);
+            return;
+        }
+        Some(Some(path)) => {
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path);
+            match file {
+                Result::Ok(file) => Some((path, file)),
+                Result::Err(error) => {
+                    eprintln!("cannot open {}: {}", path, error);
+                    return;
+                }
+            }
+        }
+    };
+
+    let mut found = 0;
+    generator::generate(games, seed, max_moves, |puzzle| {
+        found += 1;
+        match &mut out {
+            None => println!("{}", puzzle),
+            Some((path, file)) => {
+                use std::io::Write;
+                match writeln!(file, "{}", puzzle) {
+                    Result::Ok(()) => println!("{}: win in {}", puzzle.name, puzzle.moves),
+                    Result::Err(error) => eprintln!("cannot write to {}: {}", path, error),
+                }
+            }
+        }
+    });
+    eprintln!("{} puzzles from {} games with seed {}", found, games, seed);
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("tui") => {
+            let game_state = match rules_arg(&args).and_then(|rules| start_position(&args, rules)) {
+                Ok(game_state) => game_state,
+                Err(error) => {
+                    eprintln!("{}", error_chain(&error));
+                    return;
+                }
+            };
+            if let Err(error) = tui::run(game_state) {
+                eprintln!("terminal error: {}", error);
+            }
+            return;
+        }
+        Some("engine") => {
+            let rules = match rules_arg(&args) {
+                Ok(rules) => rules,
+                Err(error) => {
+                    eprintln!("{}", error_chain(&error));
+                    return;
+                }
+            };
+            if let Err(error) = engine::run(rules) {
+                eprintln!("engine error: {}", error);
+            }
+            return;
+        }
+        Some("analyze") => {
+            analyze_position(&args);
+            return;
+        }
+        Some("puzzle") => {
+            run_puzzles(&args);
+            return;
+        }
+        Some("host") => {
+            let Some(port) = port_arg(&args, DEFAULT_PORT) else {
+                eprintln!("usage: boop_game host --port N");
+                return;
+            };
+            //the joining side reads positions under the standard rules
+            match rules_arg(&args) {
+                Ok(rules) if rules == RuleSet::default() => (),
+                Ok(_) => {
+                    eprintln!("network games are played under the standard rules");
+                    return;
+                }
+                Err(error) => {
+                    eprintln!("{}", error_chain(&error));
+                    return;
+                }
+            }
+            let result = std::net::TcpListener::bind(("0.0.0.0", port)).and_then(|listener| {
+                net::run_host(
+                    listener,
+                    std::io::stdin().lock(),
+                    std::io::stdout(),
+                    use_color(&args),
+                )
+            });
+            if let Err(error) = result {
+                eprintln!("network error: {}", error);
+            }
+            return;
+        }
+        Some("serve") => {
+            let Some(port) = port_arg(&args, DEFAULT_HTTP_PORT) else {
+                eprintln!("usage: boop_game serve --port N");
+                return;
+            };
+            let rules = match rules_arg(&args) {
+                Ok(rules) => rules,
+                Err(error) => {
+                    eprintln!("{}", error_chain(&error));
+                    return;
+                }
+            };
+            let result = std::net::TcpListener::bind(("0.0.0.0", port)).and_then(|listener| {
+                println!("Serving games on http://{}", listener.local_addr()?);
+                server::run(listener, rules)
+            });
+            if let Err(error) = result {
+                eprintln!("network error: {}", error);
+            }
+            return;
+        }
+        Some("join") => {
+            let Some(address) = args.get(2) else {
+                eprintln!("usage: boop_game join <address>");
+                return;
+            };
+            let result = net::run_join(
+                address.as_str(),
+                std::io::stdin().lock(),
+                std::io::stdout(),
+                use_color(&args),
+            );
+            if let Err(error) = result {
+                eprintln!("network error: {}", error);
+            }
+            return;
+        }
+        _ => (),
+    }
+    if args
+        .windows(2)
+        .any(|pair| pair[0] == "--format" && pair[1] == "json")
+    {
+        let rules = match rules_arg(&args) {
+            Ok(rules) => rules,
+            Err(error) => {
+                eprintln!("{}", error_chain(&error));
+                return;
+            }
+        };
+        if let Err(error) = json::run(rules) {
+            eprintln!("json error: {}", error);
+        }
+        return;
+    }
+    let color = use_color(&args);
+    let mut game_state = match rules_arg(&args).and_then(|rules| start_position(&args, rules)) {
+        Ok(game_state) => game_state,
+        Err(error) => {
+            eprintln!("{}", error_chain(&error));
+            return;
+        }
+    };
+    let mut clock = match args.iter().position(|arg| arg == "--clock") {
+        Some(index) => match args
+            .get(index + 1)
+            .map(|value| value.parse::<TimeControl>())
+        {
+            Some(Ok(time_control)) => Some(Clock::new(
+                time_control,
+                game_state.turn_order.len(),
+                SystemTime::new(),
+            )),
+            Some(Err(error)) => {
+                eprintln!("{}", error_chain(&error));
+                return;
+            }
+            None => {
+                eprintln!("usage: boop_game --clock minutes[+increment|d delay]");
+                return;
+            }
+        },
+        None => None,
+    };
+
+    let autosave = args
+        .iter()
+        .position(|arg| arg == "--autosave")
+        .and_then(|index| args.get(index + 1));
+    //every move played, with the position before it so moves can be taken back
+    let mut history: Vec<(usize, Move)> = Vec::new();
+    let mut previous_states: Vec<GameState> = Vec::new();
+    let mut draw_offer: Option<usize> = None;
+    let mut drawn = false;
+    print_game_state(&game_state, color);
+
+    'turns: loop {
+        if game_state.nobody_can_move() {
+            println!("Nobody can move, the game is a draw");
+            drawn = true;
+            break;
+        }
+        //play passes for stuck players after every move, this catches a position that starts
+        //with one to move
+        let passes = game_state.pass_stuck_players();
+        print_passes(&game_state, &passes);
+        let mover = game_state.to_move();
+        if let Some(clock) = &mut clock {
+            clock.start(mover);
+            //commands that don't end the turn, like preview, keep the clock running
+            if clock.out_of_time(mover) {
+                lose_on_time(&mut game_state, mover, color);
+                break;
+            }
+            print_clock(&game_state, clock);
+        }
+        if game_state.can_swap() {
+            println!(
+                "{} can type \"swap\" to take over the opening piece instead of moving",
+                game_state.turn_order[mover].name
+            );
+        }
+        if let Some(offering) = draw_offer.filter(|offering| *offering != mover) {
+            println!(
+                "{} offers a draw, type \"accept\" to agree",
+                game_state.turn_order[offering].name
+            );
+        }
+        let Some(command) = Command::ask_player(&game_state.turn_order[mover].name) else {
+            end_of_input(&game_state, autosave);
+            break;
+        };
+        let player_move = match command {
+            Ok(Command::Place(piece_placement)) => piece_placement,
+            Ok(Command::Preview(piece_placement)) => {
+                match game_state.preview(piece_placement) {
+                    Ok(preview) => print_preview(&game_state, &preview),
+                    Err(error) => print_error(error),
+                }
+                continue;
+            }
+            Ok(Command::Swap) => {
+                if clock.as_ref().is_some_and(|clock| clock.out_of_time(mover)) {
+                    lose_on_time(&mut game_state, mover, color);
+                    break;
+                }
+                let previous_state = game_state.clone();
+                if let Err(error) = game_state.play(&Move::Swap) {
+                    print_error(error);
+                    continue;
+                }
+                println!(
+                    "{} swaps and takes the opening piece, {} moves again",
+                    game_state.turn_order[mover].name,
+                    game_state.turn_order[game_state.to_move()].name
+                );
+                previous_states.push(previous_state);
+                history.push((mover, Move::Swap));
+                draw_offer = None;
+                if let Some(loser) = clock.as_mut().and_then(Clock::stop) {
+                    lose_on_time(&mut game_state, loser, color);
+                    break;
+                }
+                print_game_state(&game_state, color);
+                continue;
+            }
+            Ok(Command::Resign) => {
+                println!("{} resigns", game_state.turn_order[mover].name);
+                game_state.winner = conceded_to(&game_state, mover);
+                print_conceded(&game_state);
+                break;
+            }
+            Ok(Command::OfferDraw) => {
+                println!("{} offers a draw", game_state.turn_order[mover].name);
+                draw_offer = Some(mover);
+                continue;
+            }
+            Ok(Command::Accept) => {
+                if draw_offer.is_some_and(|offering| offering != mover) {
+                    println!("Draw agreed");
+                    drawn = true;
+                    break;
+                }
+                println!("There is no draw offer to accept");
+                continue;
+            }
+            Ok(Command::Takeback) => {
+                //both players share the keyboard here, so no consent is asked for
+                match previous_states.pop() {
+                    Some(previous_state) => {
+                        let (player, player_move) = history.pop().expect("history matches states");
+                        println!(
+                            "Took back {}'s move {}",
+                            game_state.turn_order[player].name, player_move
+                        );
+                        game_state = previous_state;
+                        draw_offer = None;
+                        if let Some(loser) = clock.as_mut().and_then(Clock::stop) {
+                            lose_on_time(&mut game_state, loser, color);
+                            break;
+                        }
+                        print_game_state(&game_state, color);
+                    }
+                    None => println!("There is no move to take back"),
+                }
+                continue;
+            }
+            Ok(Command::Help) => {
+                println!("{}", HELP);
+                continue;
+            }
+            Ok(Command::Hint(count)) => {
+                print_analysis(&game_state, ANALYSIS_DEPTH, count);
+                continue;
+            }
+            Ok(Command::History) => {
+                let lines = history_lines(&game_state, &history);
+                if lines.is_empty() {
+                    println!("No moves played yet");
+                }
+                for line in lines {
+                    println!("{}", line);
+                }
+                continue;
+            }
+            Ok(Command::Quit) => break,
+            Err(error) => {
+                match error.kind {
+                    PiecePlacementErrorKind::Empty => (),
+                    PiecePlacementErrorKind::InvalidFormat => {
+                        print_error(error);
+                        println!("Type \"help\" for the list of commands");
+                    }
+                    _ => print_error(error),
+                };
+                continue;
+            }
+        };
+        if clock.as_ref().is_some_and(|clock| clock.out_of_time(mover)) {
+            lose_on_time(&mut game_state, mover, color);
+            break;
+        }
+        let previous_state = game_state.clone();
+        let mut line = None;
+        let passes = loop {
+            let placed = game_state.play(&Move::Place {
+                placement: player_move.clone(),
+                line,
+            });
+            match placed {
+                Ok(events) => break events,
+                Err(error) => match error.kind {
+                    //the board is shown with the piece placed and its boops made, the move
+                    //itself is played once a line is picked
+                    PieceErrorKind::MissingLineChoice => {
+                        let mut placed = game_state.clone();
+                        if placed.place_piece(player_move.clone()).is_ok() {
+                            print_game_state(&placed, color);
+                        }
+                        if let Ok(preview) = game_state.preview(player_move.clone()) {
+                            print_lines(&preview.lines);
+                        }
+                    }
+                    PieceErrorKind::InvalidLineChoice => println!("Please input valid position"),
+                    _ => {
+                        print_error(error);
+                        continue 'turns;
+                    }
+                },
+            }
+            line = match ask_constraining_coordinate() {
+                Some(Ok(constraining_coordinate)) => Some(constraining_coordinate),
+                Some(Err(error)) => {
+                    if error.kind != CoordinateErrorKind::Empty {
+                        print_error(error);
+                    }
+                    None
+                }
+                //the move is not finished, so the position before it is the one kept
+                None => {
+                    end_of_input(&game_state, autosave);
+                    return;
+                }
+            };
+        };
+        previous_states.push(previous_state);
+        history.push((
+            mover,
+            Move::Place {
+                placement: player_move,
+                line,
+            },
+        ));
+        //playing on instead of accepting turns the offer down
+        if draw_offer.is_some_and(|offering| offering != mover) {
+            draw_offer = None;
+        }
+        if let Some(loser) = clock.as_mut().and_then(Clock::stop) {
+            lose_on_time(&mut game_state, loser, color);
+            break;
+        }
+        print_game_state(&game_state, color);
+        print_passes(&game_state, &passes);
+        if let Some(winner) = game_state.winner {
+            win(&game_state.turn_order[winner].name);
+            break;
+        }
+    }
+    if (game_state.winner.is_some() || drawn) && ai::supports(&game_state) {
+        offer_review(&previous_states, &history, &game_state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_placements_with_spaces() {
+        let expected = PiecePlacement {
+            coordinate: Coordinate { x: 3, y: 4 },
+            size: Size::Small,
+        };
+        assert_eq!(
+            " s , 3 , 4 ".parse::<PiecePlacement>(),
+            Ok(expected.clone())
+        );
+        assert_eq!("small, 3, 4".parse::<PiecePlacement>(), Ok(expected));
+    }
+
+    #[test]
+    fn rejects_empty_placements() {
+        assert_eq!(
+            "".parse::<PiecePlacement>().unwrap_err().kind,
+            PiecePlacementErrorKind::Empty
+        );
+        assert_eq!(
+            "s,".parse::<PiecePlacement>().unwrap_err().kind,
+            PiecePlacementErrorKind::ValueErrorCoordinate(ParseCoordinateError {
+                kind: CoordinateErrorKind::Empty
+            })
+        );
+        assert_eq!(
+            "  ".parse::<Coordinate>().unwrap_err().kind,
+            CoordinateErrorKind::Empty
+        );
+    }
+
+    #[test]
+    fn parses_commands() {
+        let command = |input: &str| input.parse::<Command>().unwrap();
+        assert_eq!(command("resign"), Command::Resign);
+        assert_eq!(command(" offer   draw "), Command::OfferDraw);
+        assert_eq!(command("hint"), Command::Hint(DEFAULT_HINTS));
+        assert_eq!(command("hint 5"), Command::Hint(5));
+        assert_eq!(
+            command("preview s,2,3"),
+            Command::Preview("s,2,3".parse().unwrap())
+        );
+        assert_eq!(command("b,0,5"), Command::Place("b,0,5".parse().unwrap()));
+    }
+
+    #[test]
+    fn unknown_commands_are_read_as_placements() {
+        let error = "offer".parse::<Command>().unwrap_err();
+        assert_eq!(error.kind, PiecePlacementErrorKind::InvalidFormat);
+        let error = "hint many".parse::<Command>().unwrap_err();
+        assert_eq!(error.kind, PiecePlacementErrorKind::InvalidFormat);
+        let error = "preview".parse::<Command>().unwrap_err();
+        assert_eq!(error.kind, PiecePlacementErrorKind::InvalidFormat);
+    }
+
+    #[test]
+    fn error_chain_lists_every_cause() {
+        let error = BoopError::from("s,2,a".parse::<PiecePlacement>().unwrap_err());
+        assert_eq!(
+            error_chain(&error),
+            "problem parsing coordinate value: problem parsing y value: invalid digit found in string"
+        );
+    }
+
+    #[test]
+    fn renders_standard_board() {
+        let game_state: GameState = "....../..x.../...O../....../....../...... o xxxxxxx ooooooo"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            game_state.to_string(),
+            "  Player 1 (x) kittens: 7, cats: 0
+y\\x 0 1 2 3 4 5
+  0 _ _ _ _ _ _
+  1 _ _ x _ _ _
+  2 _ _ _ O _ _
+  3 _ _ _ _ _ _
+  4 _ _ _ _ _ _
+  5 _ _ _ _ _ _
+> Player 2 (o) kittens: 7, cats: 0"
+        );
+    }
+
+    #[test]
+    fn renders_columns_past_ten_lined_up() {
+        let game_state = GameState::with_rules(RuleSet {
+            width: 12,
+            height: 3,
+            ..RuleSet::default()
+        });
+        assert_eq!(
+            game_state.to_string(),
+            "> Player 1 (x) kittens: 8, cats: 0
+y\\x  0  1  2  3  4  5  6  7  8  9 10 11
+  0  _  _  _  _  _  _  _  _  _  _  _  _
+  1  _  _  _  _  _  _  _  _  _  _  _  _
+  2  _  _  _  _  _  _  _  _  _  _  _  _
+  Player 2 (o) kittens: 8, cats: 0"
+        );
+    }
+
+    #[test]
+    fn renders_colors_when_alternate() {
+        let mut game_state = GameState::init();
+        game_state
+            .play(&Move::Place {
+                placement: "s,2,3".parse().unwrap(),
+                line: None,
+            })
+            .unwrap();
+        assert_eq!(
+            format!("{:#}", game_state),
+            "  Player 1 (\x1b[31mx\x1b[0m) kittens: 7, cats: 0
+y\\x 0 1 2 3 4 5
+  0 _ _ _ _ _ _
+  1 _ _ _ _ _ _
+  2 _ _ _ _ _ _
+  3 _ _ \x1b[31;7mx\x1b[0m _ _ _
+  4 _ _ _ _ _ _
+  5 _ _ _ _ _ _
+\x1b[1m> Player 2\x1b[0m (\x1b[34mo\x1b[0m) kittens: 8, cats: 0"
+        );
+    }
+
+    #[test]
+    fn rejects_coordinates_too_big_to_pad() {
+        let game_state = GameState::init();
+        let placement: PiecePlacement = "s,18446744073709551615,0".parse().unwrap();
+        assert_eq!(
+            game_state.preview(placement).unwrap_err().kind,
+            PieceErrorKind::OutOfBoundsX
+        );
+        let huge: Coordinate = "18446744073709551615,18446744073709551615".parse().unwrap();
+        assert!(!game_state.rules.on_board(huge.padded()));
+    }
+
+    #[test]
+    fn preview_reports_boops_lines_and_winner() {
+        let game_state: GameState = "OO..../...x../....../....../....../...... o xxxxxxx ooooOO"
+            .parse()
+            .unwrap();
+        let preview = game_state.preview("b,2,0".parse().unwrap()).unwrap();
+        assert_eq!(
+            preview.boops,
+            vec![Boop {
+                from: Coordinate { x: 3, y: 1 },
+                to: Some(Coordinate { x: 4, y: 2 })
+            }]
+        );
+        assert_eq!(preview.lines.len(), 1);
+        assert!(preview.lines[0].same_cells(&Line(vec![
+            Coordinate { x: 0, y: 0 },
+            Coordinate { x: 1, y: 0 },
+            Coordinate { x: 2, y: 0 },
+        ])));
+        assert_eq!(preview.winner, Some(1));
+        assert_eq!(
+            game_state.notation(),
+            "OO..../...x../....../....../....../...... o xxxxxxx ooooOO"
+        );
+    }
+
+    #[test]
+    fn events_use_board_coordinates() {
+        let mut game_state: GameState =
+            "....../....../..x.../....../....../...... o xxxxxxx oooooooo"
+                .parse()
+                .unwrap();
+        let events = game_state
+            .play(&Move::Place {
+                placement: "s,2,3".parse().unwrap(),
+                line: None,
+            })
+            .unwrap();
+        assert_eq!(
+            events,
+            vec![
+                MoveEvent::Placed {
+                    piece: Piece {
+                        owner: 1,
+                        size: Size::Small
+                    },
+                    at: Coordinate { x: 2, y: 3 }
+                },
+                MoveEvent::Booped {
+                    from: Coordinate { x: 2, y: 2 },
+                    to: Coordinate { x: 2, y: 1 }
+                },
+            ]
+        );
+    }
+
+    //Serializes the standard opening position, lets edit tamper with the payload and returns why
+    //reading it back failed
+    #[cfg(feature = "serde")]
+    fn tampered(edit: impl FnOnce(&mut serde_json::Value)) -> String {
+        let mut payload = serde_json::to_value(GameState::init()).unwrap();
+        edit(&mut payload);
+        serde_json::from_value::<GameState>(payload)
+            .unwrap_err()
+            .to_string()
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn reads_back_untampered_payload() {
+        let mut game_state = GameState::init();
+        game_state
+            .play(&Move::Place {
+                placement: "s,2,3".parse().unwrap(),
+                line: None,
+            })
+            .unwrap();
+        let payload = serde_json::to_value(&game_state).unwrap();
+        let read_back: GameState = serde_json::from_value(payload).unwrap();
+        assert_eq!(read_back.notation(), game_state.notation());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn rejects_payload_missing_padding() {
+        let error = tampered(|payload| {
+            payload["game_board"].as_array_mut().unwrap().remove(0);
+        });
+        assert_eq!(error, "cells around the board must be out of bounds");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn rejects_payload_with_wrong_piece_count() {
+        let error = tampered(|payload| {
+            payload["turn_order"][0]["piece_pool"]
+                .as_array_mut()
+                .unwrap()
+                .pop();
+        });
+        assert_eq!(
+            error,
+            "player does not have the number of pieces the rules give them"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn rejects_payload_with_unknown_owner() {
+        let error = tampered(|payload| {
+            payload["game_board"][PADDING][PADDING] =
+                serde_json::json!({ "Piece": { "owner": 5, "size": "Small" } });
+        });
+        assert_eq!(error, "piece belongs to a player who is not playing");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn rejects_payload_with_line_on_board() {
+        let error = tampered(|payload| {
+            for x in PADDING..PADDING + 3 {
+                payload["game_board"][x][PADDING] =
+                    serde_json::json!({ "Piece": { "owner": 0, "size": "Small" } });
+                payload["turn_order"][0]["piece_pool"]
+                    .as_array_mut()
+                    .unwrap()
+                    .pop();
+            }
+        });
+        assert_eq!(
+            error,
+            "line on the board that should already have graduated"
+        );
+    }
+
+    //Why a position given in notation could not be played from
+    fn impossible(notation: &str, rules: RuleSet) -> GameStateErrorKind {
+        match GameState::from_notation(notation, rules).unwrap_err().kind {
+            NotationErrorKind::ImpossiblePosition(error) => error.kind,
+            kind => panic!("not an impossible position: {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn rejects_impossible_positions() {
+        let rules = RuleSet::default();
+        assert_eq!(
+            impossible(
+                "....../....../....../....../....../...... x xxxxxxx oooooooo",
+                rules
+            ),
+            GameStateErrorKind::WrongPieceCount
+        );
+        assert_eq!(
+            impossible(
+                "xxx.../....../....../....../....../...... o xxxxx oooooooo",
+                rules
+            ),
+            GameStateErrorKind::LineOnBoard
+        );
+        let three_cats = RuleSet {
+            cat_cap: 3,
+            ..rules
+        };
+        assert_eq!(
+            impossible(
+                "X.X.../....../X...X./....../....../...... x xxxx oooooooo",
+                three_cats
+            ),
+            GameStateErrorKind::TooManyCats
+        );
+    }
+
+    #[test]
+    fn setup_files_skip_comment_lines() {
+        let path = std::env::temp_dir().join(format!("boop_setup_{}.txt", std::process::id()));
+        let notation = "x...../....../....../....../....../...... o xxxxxxx oooooooo";
+        std::fs::write(
+            &path,
+            format!("# one kitten down\n{}\n  # played on\n", notation),
+        )
+        .unwrap();
+        let game_state = load_game(path.to_str().unwrap(), RuleSet::default());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(game_state.unwrap().notation(), notation);
+    }
+
+    fn toroidal(width: usize, height: usize, line_length: usize, pieces: usize) -> RuleSet {
+        RuleSet {
+            width,
+            height,
+            line_length,
+            pieces_per_player: pieces,
+            cat_cap: pieces,
+            toroidal: true,
+            ..RuleSet::default()
+        }
+    }
+
+    fn place(game_state: &mut GameState, placement: &str) -> Vec<MoveEvent> {
+        game_state
+            .play(&Move::Place {
+                placement: placement.parse().unwrap(),
+                line: None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn wrapped_boops_onto_the_same_cell_happen_in_turn() {
+        let rules = toroidal(4, 4, 3, 4);
+        let mut game_state =
+            GameState::from_notation("o.o./..../..../.... x xxxx oo", rules).unwrap();
+        place(&mut game_state, "s,1,0");
+        assert_eq!(game_state.notation(), ".xoo/..../..../.... o xxx oo");
+        assert!(game_state.validate().is_ok());
+    }
+
+    #[test]
+    fn ring_line_is_found_once() {
+        let rules = toroidal(4, 4, 4, 6);
+        let mut game_state =
+            GameState::from_notation("xxx./o.../.o../..o. x xxx ooo", rules).unwrap();
+        let events = place(&mut game_state, "s,3,0");
+        let lines: Vec<&MoveEvent> = events
+            .iter()
+            .filter(|event| matches!(event, MoveEvent::LineFormed(_)))
+            .collect();
+        assert_eq!(lines.len(), 1);
+        assert!(game_state.notation().starts_with("..../o.../.o../..o. o "));
+    }
+
+    #[test]
+    fn boop_wraps_round_the_edge() {
+        let rules = toroidal(6, 6, 3, 8);
+        let mut game_state = GameState::from_notation(
+            "....../....../.....o/....../....../...... x xxxxxxxx ooooooo",
+            rules,
+        )
+        .unwrap();
+        let events = place(&mut game_state, "s,0,2");
+        assert!(events.contains(&MoveEvent::Booped {
+            from: Coordinate { x: 5, y: 2 },
+            to: Coordinate { x: 4, y: 2 }
+        }));
+        assert!(game_state.notation().starts_with("....../....../x...o./"));
+    }
+
+    #[test]
+    fn wrapped_boop_can_be_blocked() {
+        let rules = toroidal(6, 6, 3, 8);
+        let mut game_state = GameState::from_notation(
+            "....../....../....oo/....../....../...... x xxxxxxxx oooooo",
+            rules,
+        )
+        .unwrap();
+        let events = place(&mut game_state, "s,0,2");
+        assert_eq!(events.len(), 1);
+        assert!(game_state.notation().starts_with("....../....../x...oo/"));
+    }
+
+    #[test]
+    fn line_runs_across_the_wrap() {
+        let rules = toroidal(6, 6, 3, 8);
+        let mut game_state = GameState::from_notation(
+            "....xx/....../....../....../....../...... x xxxxxx oooooooo",
+            rules,
+        )
+        .unwrap();
+        let events = place(&mut game_state, "s,0,0");
+        let line = events.iter().find_map(|event| match event {
+            MoveEvent::LineFormed(line) => Some(line),
+            _ => None,
+        });
+        let across = Line(vec![
+            Coordinate { x: 4, y: 0 },
+            Coordinate { x: 5, y: 0 },
+            Coordinate { x: 0, y: 0 },
+        ]);
+        assert!(line.is_some_and(|line| line.same_cells(&across)));
+        assert!(game_state.notation().starts_with("....../"));
+    }
+
+    #[test]
+    fn every_player_has_own_glyphs() {
+        let glyphs: Vec<(char, char)> = (0..MAX_PLAYERS)
+            .map(|owner| {
+                let glyph = |size| Piece { owner, size }.glyph();
+                (glyph(Size::Small), glyph(Size::Big))
+            })
+            .collect();
+        assert_eq!(glyphs, vec![('x', 'X'), ('o', 'O'), ('v', 'V'), ('z', 'Z')]);
+        assert_eq!(
+            Piece::from_glyph('Z'),
+            Some(Piece {
+                owner: 3,
+                size: Size::Big
+            })
+        );
+    }
+
+    #[test]
+    fn renders_four_player_board() {
+        let mut game_state = GameState::with_rules(RuleSet::for_players(4));
+        for placement in ["s,0,0", "s,5,0", "s,0,5", "s,5,5"] {
+            place(&mut game_state, placement);
+        }
+        assert_eq!(
+            game_state.to_string(),
+            "> Player 1 (x) kittens: 3, cats: 0
+y\\x 0 1 2 3 4 5
+  0 x _ _ _ _ o
+  1 _ _ _ _ _ _
+  2 _ _ _ _ _ _
+  3 _ _ _ _ _ _
+  4 _ _ _ _ _ _
+  5 v _ _ _ _ z
+  Player 2 (o) kittens: 3, cats: 0
+  Player 3 (v) kittens: 3, cats: 0
+  Player 4 (z) kittens: 3, cats: 0"
+        );
+    }
+
+    #[test]
+    fn third_player_graduates_own_line() {
+        let mut game_state = GameState::from_notation(
+            "vv..../....../....../....../....../...... v xxxxx ooooo vvv",
+            RuleSet::for_players(3),
+        )
+        .unwrap();
+        let events = place(&mut game_state, "s,2,0");
+        assert!(events.contains(&MoveEvent::Graduated { owner: 2 }));
+        assert_eq!(
+            game_state.notation(),
+            "....../....../....../....../....../...... x xxxxx ooooo vvVVV"
+        );
+    }
+
+    #[test]
+    fn concession_only_has_a_winner_with_two_players() {
+        assert_eq!(conceded_to(&GameState::init(), 0), Some(1));
+        assert_eq!(conceded_to(&GameState::init(), 1), Some(0));
+        let game_state = GameState::with_rules(RuleSet::for_players(3));
+        assert_eq!(conceded_to(&game_state, 1), None);
+    }
+
+    #[test]
+    fn player_out_of_pieces_cannot_move() {
+        let rules = RuleSet {
+            booped_off_to_pool: false,
+            ..RuleSet::default()
+        };
+        let game_state =
+            GameState::from_notation("x...../....../....../....../....../...... x - ooo", rules)
+                .unwrap();
+        assert!(!game_state.can_move());
+        assert!(!game_state.nobody_can_move());
 
-        game_state.display();
+        let game_state =
+            GameState::from_notation("x...../....../....../....../....../...... x - -", rules)
+                .unwrap();
+        assert!(game_state.nobody_can_move());
     }
 }
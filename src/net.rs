@@ -0,0 +1,395 @@
+//Two player games over TCP. The host keeps the authoritative GameState, checks every move the
+//remote player sends and pushes the position to both ends after each change.
+//
+//Messages from the host are single lines:
+//  state <notation>        the current position
+//  your_move <name>        the remote player should send a move like "s,2,3"
+//  choose_line <name>      the remote player should send a piece of the line to graduate
+//  ask <question>          the remote player should answer, "accept" agrees to a takeback or draw
+//  message <text>          something to show the remote player
+//  error <text>            the last thing the remote player sent was rejected
+//  gameover <text>         the game has ended
+//The remote player answers your_move, choose_line and ask with the line they typed. Instead of a
+//move they can send any of the in-game commands, takebacks and draws need the other player to agree
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+};
+
+use crate::{
+    error_chain, Command, Coordinate, GameState, Move, MoveEvent, PieceErrorKind, PiecePlacement,
+    HELP,
+};
+
+const HOST_PLAYER: usize = 0;
+
+enum Request {
+    Move,
+    Line,
+    Question(String),
+}
+
+struct Remote {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+impl Remote {
+    fn new(stream: TcpStream) -> io::Result<Remote> {
+        Ok(Remote {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        })
+    }
+
+    fn send(&mut self, message: &str) -> io::Result<()> {
+        writeln!(self.writer, "{}", message)?;
+        self.writer.flush()
+    }
+
+    //None once the other end has hung up
+    fn receive(&mut self) -> io::Result<Option<String>> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(line.trim().to_string()))
+    }
+}
+
+fn render(game_state: &GameState, color: bool) -> String {
+    if color {
+        format!("{:#}", game_state)
+    } else {
+        format!("{}", game_state)
+    }
+}
+
+struct Host<I, O> {
+    game_state: GameState,
+    input: I,
+    output: O,
+    remote: Remote,
+    color: bool,
+}
+impl<I: BufRead, O: Write> Host<I, O> {
+    fn broadcast_state(&mut self) -> io::Result<()> {
+        writeln!(self.output, "{}", render(&self.game_state, self.color))?;
+        self.remote
+            .send(&format!("state {}", self.game_state.notation()))
+    }
+
+    fn tell(&mut self, player: usize, message: &str) -> io::Result<()> {
+        if player == HOST_PLAYER {
+            writeln!(self.output, "{}", message)
+        } else {
+            self.remote.send(&format!("message {}", message))
+        }
+    }
+
+    //Asks the other player to agree to something, None when they have left
+    fn agree(&mut self, asking: usize, question: &str) -> io::Result<Option<bool>> {
+        let other = (asking + 1) % 2;
+        let answer = self.ask(other, Request::Question(question.to_string()))?;
+        Ok(answer.map(|answer| matches!(answer.parse(), Ok(Command::Accept))))
+    }
+
+    fn tell_error(&mut self, player: usize, message: &str) -> io::Result<()> {
+        if player == HOST_PLAYER {
+            writeln!(self.output, "{}", message)
+        } else {
+            self.remote.send(&format!("error {}", message))
+        }
+    }
+
+    //Asks whoever owns the decision and returns what they typed, None when they have left
+    fn ask(&mut self, player: usize, request: Request) -> io::Result<Option<String>> {
+        let name = self.game_state.turn_order[player].name.clone();
+        if player != HOST_PLAYER {
+            writeln!(self.output, "Waiting for {}", name)?;
+            let message = match request {
+                Request::Move => format!("your_move {}", name),
+                Request::Line => format!("choose_line {}", name),
+                Request::Question(question) => format!("ask {}", question),
+            };
+            self.remote.send(&message)?;
+            return self.remote.receive();
+        }
+
+        self.remote.send(&format!("message Waiting for {}", name))?;
+        match request {
+            Request::Move => writeln!(self.output, "{} to move:", name)?,
+            Request::Line => writeln!(self.output, "Select constraining Piece")?,
+            Request::Question(question) => writeln!(self.output, "{}", question)?,
+        }
+        self.output.flush()?;
+        let mut line = String::new();
+        if self.input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(line.trim().to_string()))
+    }
+
+    //Shows the player the lines of three their placement would make, so they can pick one
+    fn tell_lines(&mut self, player: usize, placement: &PiecePlacement) -> io::Result<()> {
+        let lines = match self.game_state.preview(placement.clone()) {
+            Ok(preview) => preview.lines,
+            Err(_) => Vec::new(),
+        };
+        for line in lines {
+            let cells: Vec<String> = line.0.iter().map(Coordinate::to_string).collect();
+            self.tell(player, &format!("Line at {}", cells.join(" ")))?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, message: &str) -> io::Result<()> {
+        writeln!(self.output, "{}", message)?;
+        //the other side may already be gone, there is nobody left to tell then
+        let _ = self.remote.send(&format!("gameover {}", message));
+        Ok(())
+    }
+
+    fn run(&mut self) -> io::Result<()> {
+        let mut history: Vec<(usize, Move)> = Vec::new();
+        let mut previous_states: Vec<GameState> = Vec::new();
+        self.broadcast_state()?;
+
+        'turns: loop {
+            let mover = self.game_state.to_move();
+            let name = self.game_state.turn_order[mover].name.clone();
+            let Some(text) = self.ask(mover, Request::Move)? else {
+                return self.finish(&format!("{} left the game", name));
+            };
+            let placement = match text.parse::<Command>() {
+                Ok(Command::Place(placement)) => placement,
+                Ok(Command::Preview(_)) => {
+                    self.tell_error(mover, "preview is only available in local games")?;
+                    continue;
+                }
+                Ok(Command::Swap) => {
+                    self.tell_error(mover, "the swap rule is only available in local games")?;
+                    continue;
+                }
+                Ok(Command::Hint(_)) => {
+                    self.tell_error(mover, "hints are only available in local games")?;
+                    continue;
+                }
+                Ok(Command::Resign) => {
+                    let winner = &self.game_state.turn_order[(mover + 1) % 2].name;
+                    let message = format!("{} resigns, {} wins!", name, winner);
+                    return self.finish(&message);
+                }
+                Ok(Command::OfferDraw) => {
+                    let question = format!("{} offers a draw, type \"accept\" to agree", name);
+                    match self.agree(mover, &question)? {
+                        Some(true) => return self.finish("Draw agreed"),
+                        Some(false) => self.tell(mover, "Draw offer declined")?,
+                        None => return self.finish("Opponent left the game"),
+                    }
+                    continue;
+                }
+                Ok(Command::Accept) => {
+                    self.tell_error(mover, "There is no draw offer to accept")?;
+                    continue;
+                }
+                Ok(Command::Takeback) => {
+                    let Some((player, player_move)) = history.last().cloned() else {
+                        self.tell_error(mover, "There is no move to take back")?;
+                        continue;
+                    };
+                    let question = format!(
+                        "{} asks to take back {}'s move {}, type \"accept\" to agree",
+                        name, self.game_state.turn_order[player].name, player_move
+                    );
+                    match self.agree(mover, &question)? {
+                        Some(true) => {
+                            history.pop();
+                            if let Some(previous_state) = previous_states.pop() {
+                                self.game_state = previous_state;
+                            }
+                            self.broadcast_state()?;
+                        }
+                        Some(false) => self.tell(mover, "Takeback declined")?,
+                        None => return self.finish("Opponent left the game"),
+                    }
+                    continue;
+                }
+                Ok(Command::Help) => {
+                    for line in HELP.lines() {
+                        self.tell(mover, line)?;
+                    }
+                    continue;
+                }
+                Ok(Command::History) => {
+                    let lines = crate::history_lines(&self.game_state, &history);
+                    if lines.is_empty() {
+                        self.tell(mover, "No moves played yet")?;
+                    }
+                    for line in lines {
+                        self.tell(mover, &line)?;
+                    }
+                    continue;
+                }
+                Ok(Command::Quit) => return self.finish(&format!("{} left the game", name)),
+                Err(error) => {
+                    self.tell_error(mover, &error_chain(&error))?;
+                    continue;
+                }
+            };
+            let previous_state = self.game_state.clone();
+            let mut player_move = Move::Place {
+                placement: placement.clone(),
+                line: None,
+            };
+            let events = loop {
+                match self.game_state.play(&player_move) {
+                    Ok(events) => break events,
+                    Err(error) => match error.kind {
+                        PieceErrorKind::MissingLineChoice => self.tell_lines(mover, &placement)?,
+                        PieceErrorKind::InvalidLineChoice => {
+                            self.tell_error(mover, "Please input valid position")?
+                        }
+                        _ => {
+                            self.tell_error(mover, &error.to_string())?;
+                            continue 'turns;
+                        }
+                    },
+                }
+                let Some(text) = self.ask(mover, Request::Line)? else {
+                    return self.finish(&format!("{} left the game", name));
+                };
+                match text.parse::<Coordinate>() {
+                    Ok(coordinate) => {
+                        player_move = Move::Place {
+                            placement: placement.clone(),
+                            line: Some(coordinate),
+                        }
+                    }
+                    Err(error) => self.tell_error(mover, &error_chain(&error))?,
+                }
+            };
+            previous_states.push(previous_state);
+            history.push((mover, player_move));
+
+            self.broadcast_state()?;
+            if let Some(winner) = self.game_state.winner {
+                let message = format!("{} wins!", self.game_state.turn_order[winner].name);
+                return self.finish(&message);
+            }
+            if self.game_state.nobody_can_move() {
+                return self.finish("Nobody can move, the game is a draw");
+            }
+            for event in events {
+                if let MoveEvent::Passed { player } = event {
+                    let message = format!(
+                        "{} has no pieces left to place and passes",
+                        self.game_state.turn_order[player].name
+                    );
+                    self.tell(HOST_PLAYER, &message)?;
+                    self.tell(1 - HOST_PLAYER, &message)?;
+                }
+            }
+        }
+    }
+}
+
+//Waits for one opponent on the listener and plays the first player from input/output
+pub fn run_host(
+    listener: TcpListener,
+    input: impl BufRead,
+    mut output: impl Write,
+    color: bool,
+) -> io::Result<()> {
+    writeln!(
+        output,
+        "Waiting for an opponent on {}",
+        listener.local_addr()?
+    )?;
+    let (stream, address) = listener.accept()?;
+    writeln!(output, "{} joined", address)?;
+
+    let mut host = Host {
+        game_state: GameState::init(),
+        input,
+        output,
+        remote: Remote::new(stream)?,
+        color,
+    };
+    host.run()
+}
+
+//Plays the second player against a host, relaying what is typed on input
+pub fn run_join(
+    address: impl ToSocketAddrs,
+    mut input: impl BufRead,
+    mut output: impl Write,
+    color: bool,
+) -> io::Result<()> {
+    let mut remote = Remote::new(TcpStream::connect(address)?)?;
+
+    while let Some(message) = remote.receive()? {
+        let (kind, rest) = message.split_once(' ').unwrap_or((&message, ""));
+        match kind {
+            "state" => match rest.parse::<GameState>() {
+                Ok(game_state) => writeln!(output, "{}", render(&game_state, color))?,
+                Err(error) => {
+                    writeln!(output, "host sent a bad position: {}", error_chain(&error))?
+                }
+            },
+            "your_move" | "choose_line" | "ask" => {
+                match kind {
+                    "your_move" => writeln!(output, "{} to move:", rest)?,
+                    "choose_line" => writeln!(output, "Select constraining Piece")?,
+                    _ => writeln!(output, "{}", rest)?,
+                }
+                output.flush()?;
+                let mut line = String::new();
+                if input.read_line(&mut line)? == 0 {
+                    return Ok(());
+                }
+                remote.send(line.trim())?;
+            }
+            "message" | "error" => writeln!(output, "{}", rest)?,
+            "gameover" => {
+                writeln!(output, "{}", rest)?;
+                return Ok(());
+            }
+            _ => writeln!(output, "unknown message from host: {}", message)?,
+        }
+    }
+    writeln!(output, "host disconnected")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{io::Cursor, thread};
+
+    #[test]
+    fn plays_a_short_game_over_localhost() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let host = thread::spawn(move || {
+            let input = Cursor::new("s,0,0\ns,5,5\ns,0,5\n");
+            let mut output = Vec::new();
+            run_host(listener, input, &mut output, false).unwrap();
+            String::from_utf8(output).unwrap()
+        });
+
+        //the first move is onto the host's piece and has to be sent again
+        let input = Cursor::new("s,0,0\ns,2,2\ns,3,3\nresign\n");
+        let mut output = Vec::new();
+        run_join(address, input, &mut output, false).unwrap();
+        let joined = String::from_utf8(output).unwrap();
+        let hosted = host.join().unwrap();
+
+        assert!(joined.contains("attempting to place piece at occupied position"));
+        assert!(hosted.ends_with("Player 2 resigns, Player 1 wins!\n"));
+        assert!(joined.ends_with("Player 2 resigns, Player 1 wins!\n"));
+        //the host's last move and the boop of 2,2 to 1,1 reached both ends
+        for output in [&hosted, &joined] {
+            assert!(output.contains("  1 _ o _ _ _ _\n"));
+            assert!(output.contains("  5 x _ _ _ _ x\n"));
+        }
+    }
+}
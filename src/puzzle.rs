@@ -0,0 +1,518 @@
+//"Win in N" puzzles: positions where the side to move can force a win within N of their own
+//moves whatever the others reply. The solver here proves it, checks the player's moves against
+//every winning line and finds the reply that refutes a move that doesn't win
+use std::{error::Error, fmt};
+
+use crate::{
+    print_error, print_game_state, read_input, Cell, GameState, Move, ParseNotationError,
+    PiecePlacement, Size,
+};
+
+#[derive(Debug)]
+pub struct InvalidPuzzleError {
+    kind: PuzzleErrorKind,
+}
+#[derive(Debug)]
+enum PuzzleErrorKind {
+    InvalidPosition(ParseNotationError),
+    NoMoves,
+    NotAForcedWin,
+    QuickerWin,
+}
+impl InvalidPuzzleError {
+    pub fn __description(&self) -> &str {
+        match self.kind {
+            PuzzleErrorKind::InvalidPosition(_) => "puzzle position is not a valid position",
+            PuzzleErrorKind::NoMoves => "puzzle needs at least one move to win in",
+            PuzzleErrorKind::NotAForcedWin => "puzzle is not a forced win in its number of moves",
+            PuzzleErrorKind::QuickerWin => "puzzle can be won in fewer moves than it says",
+        }
+    }
+}
+impl fmt::Display for InvalidPuzzleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.__description().fmt(f)
+    }
+}
+impl Error for InvalidPuzzleError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            PuzzleErrorKind::InvalidPosition(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParsePuzzleError {
+    line: usize,
+    kind: ParsePuzzleErrorKind,
+}
+#[derive(Debug)]
+enum ParsePuzzleErrorKind {
+    InvalidLine,
+    UnknownKey(String),
+    DuplicateKey(String),
+    MissingKey(&'static str),
+    InvalidValue(String),
+    InvalidPosition(ParseNotationError),
+}
+impl ParsePuzzleError {
+    pub fn __description(&self) -> &str {
+        match self.kind {
+            ParsePuzzleErrorKind::InvalidLine => "line should be \"key = value\"",
+            ParsePuzzleErrorKind::UnknownKey(_) => "unknown puzzle key",
+            ParsePuzzleErrorKind::DuplicateKey(_) => "puzzle key set more than once",
+            ParsePuzzleErrorKind::MissingKey(_) => "puzzle is missing",
+            ParsePuzzleErrorKind::InvalidValue(_) => "puzzle value should be a number for",
+            ParsePuzzleErrorKind::InvalidPosition(_) => "invalid puzzle position",
+        }
+    }
+}
+//Names the key and the line at fault, a puzzle's line is the first line of its block
+impl fmt::Display for ParsePuzzleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ParsePuzzleErrorKind::UnknownKey(key)
+            | ParsePuzzleErrorKind::DuplicateKey(key)
+            | ParsePuzzleErrorKind::InvalidValue(key) => {
+                write!(f, "{} \"{}\"", self.__description(), key)?
+            }
+            ParsePuzzleErrorKind::MissingKey(key) => {
+                write!(f, "{} \"{}\"", self.__description(), key)?
+            }
+            _ => self.__description().fmt(f)?,
+        }
+        write!(f, " on line {}", self.line)
+    }
+}
+impl Error for ParsePuzzleError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            ParsePuzzleErrorKind::InvalidPosition(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Puzzle {
+    pub name: String,
+    //Position in the engine protocol's notation, played under the standard rules
+    pub position: String,
+    //How many of their own moves the side to move needs to win
+    pub moves: usize,
+    //From 1 up, two for every move less one when a bot looking one move ahead finds the solution
+    pub difficulty: Option<usize>,
+    //What the winning move does, like "double boop" or "edge push-off"
+    pub theme: Option<String>,
+}
+//Written in the puzzle format, so generated puzzles can be saved and read back
+impl fmt::Display for Puzzle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "name = {}", self.name)?;
+        writeln!(f, "position = {}", self.position)?;
+        writeln!(f, "moves = {}", self.moves)?;
+        if let Some(difficulty) = self.difficulty {
+            writeln!(f, "difficulty = {}", difficulty)?;
+        }
+        if let Some(theme) = &self.theme {
+            writeln!(f, "theme = {}", theme)?;
+        }
+        Ok(())
+    }
+}
+
+//The collection that comes with the game
+pub fn built_in() -> Vec<Puzzle> {
+    parse_puzzles(include_str!("puzzles.txt")).expect("built-in puzzles are valid")
+}
+
+//Reads puzzles in the puzzle format: one block of "key = value" lines for every puzzle with blank
+//lines between them and "#" comments. The keys are name, position, moves, difficulty and theme,
+//and the first three are needed
+pub fn parse_puzzles(text: &str) -> Result<Vec<Puzzle>, ParsePuzzleError> {
+    let mut puzzles = Vec::new();
+    let mut block: Vec<(usize, &str, &str)> = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            if !block.is_empty() {
+                puzzles.push(parse_puzzle(&block)?);
+                block.clear();
+            }
+            continue;
+        }
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(ParsePuzzleError {
+                line: index + 1,
+                kind: ParsePuzzleErrorKind::InvalidLine,
+            });
+        };
+        block.push((index + 1, key.trim(), value.trim()));
+    }
+    if !block.is_empty() {
+        puzzles.push(parse_puzzle(&block)?);
+    }
+    Ok(puzzles)
+}
+
+fn parse_puzzle(block: &[(usize, &str, &str)]) -> Result<Puzzle, ParsePuzzleError> {
+    let mut name = None;
+    let mut position = None;
+    let mut moves = None;
+    let mut difficulty = None;
+    let mut theme = None;
+    for (line, key, value) in block {
+        let at_line = |kind| ParsePuzzleError { line: *line, kind };
+        let number = || {
+            value
+                .parse::<usize>()
+                .map_err(|_| at_line(ParsePuzzleErrorKind::InvalidValue(key.to_string())))
+        };
+        let duplicate = match *key {
+            "name" => name.replace(value.to_string()).is_some(),
+            "position" => {
+                value
+                    .parse::<GameState>()
+                    .map_err(|error| at_line(ParsePuzzleErrorKind::InvalidPosition(error)))?;
+                position.replace(value.to_string()).is_some()
+            }
+            "moves" => moves.replace(number()?).is_some(),
+            "difficulty" => difficulty.replace(number()?).is_some(),
+            "theme" => theme.replace(value.to_string()).is_some(),
+            _ => return Err(at_line(ParsePuzzleErrorKind::UnknownKey(key.to_string()))),
+        };
+        if duplicate {
+            return Err(at_line(ParsePuzzleErrorKind::DuplicateKey(key.to_string())));
+        }
+    }
+
+    let missing = |key| ParsePuzzleError {
+        line: block[0].0,
+        kind: ParsePuzzleErrorKind::MissingKey(key),
+    };
+    Ok(Puzzle {
+        name: name.ok_or_else(|| missing("name"))?,
+        position: position.ok_or_else(|| missing("position"))?,
+        moves: moves.ok_or_else(|| missing("moves"))?,
+        difficulty,
+        theme,
+    })
+}
+
+impl Puzzle {
+    pub fn game_state(&self) -> Result<GameState, InvalidPuzzleError> {
+        self.position.parse().map_err(|error| InvalidPuzzleError {
+            kind: PuzzleErrorKind::InvalidPosition(error),
+        })
+    }
+
+    //Runs the solver over the puzzle: it has to be a forced win in exactly its number of moves
+    pub fn check(&self) -> Result<(), InvalidPuzzleError> {
+        let game_state = self.game_state()?;
+        if self.moves == 0 {
+            return Err(InvalidPuzzleError {
+                kind: PuzzleErrorKind::NoMoves,
+            });
+        }
+        if !wins_within(&game_state, self.moves) {
+            return Err(InvalidPuzzleError {
+                kind: PuzzleErrorKind::NotAForcedWin,
+            });
+        }
+        if wins_within(&game_state, self.moves - 1) {
+            return Err(InvalidPuzzleError {
+                kind: PuzzleErrorKind::QuickerWin,
+            });
+        }
+        Ok(())
+    }
+}
+
+//Whether the player to move can force a win within moves of their own moves
+pub fn wins_within(game_state: &GameState, moves: usize) -> bool {
+    let attacker = game_state.to_move();
+    match moves {
+        0 => false,
+        1 => wins_now(game_state),
+        _ => children(game_state).any(|(_, after)| wins_after(&after, attacker, moves)),
+    }
+}
+
+//Every move that keeps a forced win within moves of the player's own moves, counting this one
+pub fn winning_moves(game_state: &GameState, moves: usize) -> Vec<Move> {
+    let attacker = game_state.to_move();
+    children(game_state)
+        .filter(|(_, after)| wins_after(after, attacker, moves))
+        .map(|(player_move, _)| player_move)
+        .collect()
+}
+
+//Whether player_move forces a win for the player to move within moves of their own moves,
+//counting player_move itself
+pub fn wins_with(game_state: &GameState, player_move: &Move, moves: usize) -> bool {
+    let attacker = game_state.to_move();
+    let mut after = game_state.clone();
+    moves > 0 && after.play(player_move).is_ok() && wins_after(&after, attacker, moves)
+}
+
+//A reply by the defender to move that stops attacker winning within moves more of their own
+//moves, None when every reply loses
+pub fn refutation(game_state: &GameState, attacker: usize, moves: usize) -> Option<Move> {
+    children(game_state)
+        .find(|(_, after)| escapes(after, attacker, moves))
+        .map(|(reply, _)| reply)
+}
+
+//The defender's reply that puts the attacker's win off longest, so the puzzle never gets easier
+//than the solution says
+pub fn defence(game_state: &GameState, attacker: usize, moves: usize) -> Option<Move> {
+    children(game_state)
+        .max_by_key(|(_, after)| {
+            (1..=moves)
+                .find(|moves| !escapes(after, attacker, *moves))
+                .unwrap_or(moves + 1)
+        })
+        .map(|(reply, _)| reply)
+}
+
+//Every legal move with the position it leads to, in the order of GameState::legal_moves. The
+//positions are made one at a time, as most of the time the solver only needs the first few
+fn children(game_state: &GameState) -> impl Iterator<Item = (Move, GameState)> + '_ {
+    game_state
+        .legal_moves()
+        .into_iter()
+        .filter_map(|player_move| {
+            let mut after = game_state.clone();
+            after.play(&player_move).ok()?;
+            Some((player_move, after))
+        })
+}
+
+//Whether the player to move can win with this move, which is most of the solver's work so it
+//looks at previews. Kittens can't boop cats, so only placing a cat can make a line of cats or
+//leave nothing but cats on the board, and a line of cats needs cats on the board already
+fn wins_now(game_state: &GameState) -> bool {
+    let mover = game_state.to_move();
+    let has_cat = game_state.turn_order[mover]
+        .piece_pool
+        .iter()
+        .any(|piece| piece.size == Size::Big);
+    let cats_on_board = game_state
+        .rules
+        .board_cells()
+        .filter(|c| {
+            matches!(
+                &game_state.game_board[c.x][c.y],
+                Cell::Piece(piece) if piece.owner == mover && piece.size == Size::Big
+            )
+        })
+        .count();
+    let enough_cats = cats_on_board + 1 >= game_state.rules.line_length
+        || game_state.rules.all_cats_on_board_wins;
+    game_state.winner.is_none()
+        && has_cat
+        && enough_cats
+        && game_state
+            .rules
+            .board_cells()
+            .filter(|c| game_state.game_board[c.x][c.y] == Cell::Empty)
+            .any(|c| {
+                let placement = PiecePlacement {
+                    coordinate: c.board_coordinate(),
+                    size: Size::Big,
+                };
+                game_state
+                    .preview(placement)
+                    .is_ok_and(|preview| preview.winner == Some(mover))
+            })
+}
+
+//Whether attacker, who just moved into after, wins within moves of their own moves counting the
+//one just played
+fn wins_after(after: &GameState, attacker: usize, moves: usize) -> bool {
+    match after.winner {
+        Some(winner) => winner == attacker,
+        None => moves > 1 && !holds(after, attacker, moves - 1),
+    }
+}
+
+//Whether the defender to move has a reply that stops attacker winning within moves more of
+//their own moves. A defender without any legal move is stuck, which is no win for anyone
+fn holds(game_state: &GameState, attacker: usize, moves: usize) -> bool {
+    let mut replies = children(game_state).peekable();
+    replies.peek().is_none() || replies.any(|(_, after)| escapes(&after, attacker, moves))
+}
+
+//Whether a defender's reply that led to after keeps attacker from winning within moves more of
+//their own moves
+fn escapes(after: &GameState, attacker: usize, moves: usize) -> bool {
+    match after.winner {
+        Some(winner) => winner != attacker,
+        None if after.to_move() == attacker => !wins_within(after, moves),
+        None => holds(after, attacker, moves),
+    }
+}
+
+//Plays a puzzle on stdin/stdout. A move that doesn't win is shown its refutation and the player
+//tries again from the same position, any move that still wins in time is accepted
+pub fn run(puzzle: &Puzzle, color: bool) -> Result<(), InvalidPuzzleError> {
+    let mut game_state = puzzle.game_state()?;
+    let solver = game_state.to_move();
+    let solver_name = game_state.turn_order[solver].name.clone();
+    println!(
+        "{}: {} to move and win in {}",
+        puzzle.name, solver_name, puzzle.moves
+    );
+    print_game_state(&game_state, color);
+
+    let mut moves_left = puzzle.moves;
+    loop {
+        println!("{} to move ({} left):", solver_name, moves_left);
+        let Some(input) = read_input() else {
+            println!();
+            return Ok(());
+        };
+        if input == "quit" {
+            return Ok(());
+        }
+        if input == "solution" {
+            let moves: Vec<String> = winning_moves(&game_state, moves_left)
+                .iter()
+                .map(Move::to_string)
+                .collect();
+            println!("Winning moves: {}", moves.join(" "));
+            continue;
+        }
+        let player_move = match input.parse::<Move>() {
+            Ok(player_move) => player_move,
+            Err(error) => {
+                print_error(error);
+                continue;
+            }
+        };
+        let mut after = game_state.clone();
+        if let Err(error) = after.play(&player_move) {
+            print_error(error);
+            continue;
+        }
+
+        if !wins_with(&game_state, &player_move, moves_left) {
+            match after.winner {
+                Some(winner) => println!(
+                    "{} hands the win to {}",
+                    player_move, after.turn_order[winner].name
+                ),
+                None => println!("{} does not win in {}", player_move, moves_left),
+            }
+            let reply = match moves_left {
+                1 => None,
+                _ => refutation(&after, solver, moves_left - 1),
+            };
+            if let Some(reply) = reply {
+                let defender = after.to_move();
+                after.play(&reply).ok();
+                println!("{} answers {}", after.turn_order[defender].name, reply);
+                print_game_state(&after, color);
+            }
+            println!("Try again");
+            print_game_state(&game_state, color);
+            continue;
+        }
+
+        game_state = after;
+        if game_state.winner.is_some() {
+            print_game_state(&game_state, color);
+            println!("Solved!");
+            return Ok(());
+        }
+        moves_left -= 1;
+        while game_state.to_move() != solver {
+            let defender = game_state.to_move();
+            let Some(reply) = defence(&game_state, solver, moves_left) else {
+                break;
+            };
+            game_state.play(&reply).ok();
+            println!("{} answers {}", game_state.turn_order[defender].name, reply);
+        }
+        print_game_state(&game_state, color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_puzzles_are_sound() {
+        let puzzles = built_in();
+        assert!(!puzzles.is_empty());
+        for puzzle in puzzles {
+            if let Err(error) = puzzle.check() {
+                panic!("{}: {}", puzzle.name, crate::error_chain(&error));
+            }
+        }
+    }
+
+    fn parse_error(text: &str) -> (usize, String) {
+        match parse_puzzles(text) {
+            Ok(_) => panic!("{:?} should not parse", text),
+            Err(error) => (error.line, error.to_string()),
+        }
+    }
+
+    #[test]
+    fn reads_puzzles_back() {
+        let puzzles = built_in();
+        let text: Vec<String> = puzzles.iter().map(Puzzle::to_string).collect();
+        let read = parse_puzzles(&text.join("\n")).unwrap();
+        assert_eq!(read.len(), puzzles.len());
+        assert_eq!(read[0].position, puzzles[0].position);
+        assert_eq!(read[0].theme, puzzles[0].theme);
+    }
+
+    #[test]
+    fn rejects_bad_puzzle_files() {
+        let position = "x...../..X.X./o.x.o./..o.Xo/..o.../...... x XXX ooo";
+        assert_eq!(
+            parse_error(&format!(
+                "name = a\nposition = {}\nmoves = 1\nhint = b",
+                position
+            )),
+            (4, "unknown puzzle key \"hint\" on line 4".to_string())
+        );
+        assert_eq!(
+            parse_error(&format!(
+                "name = a\nname = b\nposition = {}\nmoves = 1",
+                position
+            )),
+            (
+                2,
+                "puzzle key set more than once \"name\" on line 2".to_string()
+            )
+        );
+        assert_eq!(
+            parse_error(&format!("# comment\n\nname = a\nposition = {}", position)),
+            (3, "puzzle is missing \"moves\" on line 3".to_string())
+        );
+        assert_eq!(
+            parse_error("name = a\nposition = x... x XXX ooo\nmoves = 1"),
+            (2, "invalid puzzle position on line 2".to_string())
+        );
+        assert_eq!(
+            parse_error(&format!("name = a\nposition = {}\nmoves = one", position)),
+            (
+                3,
+                "puzzle value should be a number for \"moves\" on line 3".to_string()
+            )
+        );
+        assert_eq!(
+            parse_error("name a"),
+            (1, "line should be \"key = value\" on line 1".to_string())
+        );
+    }
+}
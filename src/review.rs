@@ -0,0 +1,221 @@
+//Post-game review: every move of a finished game is searched again and judged by how much of the
+//position's score it gave away compared with the bot's choice
+use std::fmt;
+
+use crate::{
+    ai::{self, Limit},
+    score_text, GameState, Move,
+};
+
+const REVIEW_DEPTH: u32 = 3;
+//Score a move can lose before it is marked, in ai::evaluate's units where a kitten on the board is
+//worth 10 and a cat 115
+const INACCURACY_LOSS: i32 = 15;
+const MISTAKE_LOSS: i32 = 60;
+const BLUNDER_LOSS: i32 = 150;
+//Most a single move counts for in the average loss, so one missed forced win doesn't drown out
+//the rest of the game
+const MAX_AVERAGED_LOSS: i32 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Judgement {
+    Best,
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+impl Judgement {
+    fn from_loss(loss: i32) -> Judgement {
+        match loss {
+            loss if loss >= BLUNDER_LOSS => Judgement::Blunder,
+            loss if loss >= MISTAKE_LOSS => Judgement::Mistake,
+            loss if loss >= INACCURACY_LOSS => Judgement::Inaccuracy,
+            _ => Judgement::Best,
+        }
+    }
+
+    //Chess annotation marks
+    fn mark(self) -> &'static str {
+        match self {
+            Judgement::Best => "",
+            Judgement::Inaccuracy => "?!",
+            Judgement::Mistake => "?",
+            Judgement::Blunder => "??",
+        }
+    }
+}
+impl fmt::Display for Judgement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Judgement::Best => write!(f, "best"),
+            Judgement::Inaccuracy => write!(f, "inaccuracy"),
+            Judgement::Mistake => write!(f, "mistake"),
+            Judgement::Blunder => write!(f, "blunder"),
+        }
+    }
+}
+
+//A played move next to the bot's choice, both scored from the mover's point of view
+pub struct MoveReview {
+    pub player: usize,
+    pub played: Move,
+    pub played_score: i32,
+    pub best: Move,
+    pub best_score: i32,
+}
+impl MoveReview {
+    pub fn loss(&self) -> i32 {
+        (self.best_score - self.played_score).max(0)
+    }
+
+    pub fn judgement(&self) -> Judgement {
+        Judgement::from_loss(self.loss())
+    }
+}
+
+//None when the move was not legal in before
+pub fn review_move(before: &GameState, played: &Move) -> Option<MoveReview> {
+    let best = ai::search(before, Limit::Depth(REVIEW_DEPTH), |_| ())?;
+    let best_move = best.pv.first()?.clone();
+    let played_score = if *played == best_move {
+        best.score
+    } else {
+        ai::analyze_move(before, played, REVIEW_DEPTH)?.score
+    };
+    Some(MoveReview {
+        player: before.to_move(),
+        played: played.clone(),
+        played_score,
+        best: best_move,
+        best_score: best.score,
+    })
+}
+
+//Reviews a finished game from the positions before every move and the moves played, printing
+//each move as it is judged and then a summary for every player. Returns the annotated game
+//record: the same report with the starting position, the result and the summaries as "#" comments
+pub fn run(previous_states: &[GameState], history: &[(usize, Move)], end: &GameState) -> String {
+    let Some(start) = previous_states.first() else {
+        println!("No moves to review");
+        return String::new();
+    };
+    let mut record = vec![
+        String::from("# annotated boop game, ?! marks an inaccuracy, ? a mistake and ?? a blunder"),
+        format!("# start: {}", start.notation()),
+    ];
+
+    let mut reviews = Vec::new();
+    for (index, (before, (_, played))) in previous_states.iter().zip(history).enumerate() {
+        let Some(review) = review_move(before, played) else {
+            continue;
+        };
+        let name = &end.turn_order[review.player].name;
+        let line = match review.judgement() {
+            Judgement::Best => format!(
+                "{}. {}: {} best ({})",
+                index + 1,
+                name,
+                review.played,
+                score_text(review.played_score)
+            ),
+            judgement => format!(
+                "{}. {}: {} {} {} ({}), best was {} ({})",
+                index + 1,
+                name,
+                review.played,
+                judgement.mark(),
+                judgement,
+                score_text(review.played_score),
+                review.best,
+                score_text(review.best_score)
+            ),
+        };
+        println!("{}", line);
+        record.push(line);
+        reviews.push(review);
+    }
+
+    let result = match end.winner {
+        Some(winner) => format!("{} wins", end.turn_order[winner].name),
+        None => String::from("draw"),
+    };
+    record.push(format!("# result: {}", result));
+    for (player_index, player) in end.turn_order.iter().enumerate() {
+        let reviews: Vec<&MoveReview> = reviews
+            .iter()
+            .filter(|review| review.player == player_index)
+            .collect();
+        let count = |judgement| {
+            reviews
+                .iter()
+                .filter(|review| review.judgement() == judgement)
+                .count()
+        };
+        let total_loss: i32 = reviews
+            .iter()
+            .map(|review| review.loss().min(MAX_AVERAGED_LOSS))
+            .sum();
+        let summary = format!(
+            "{}: best {}, inaccuracies {}, mistakes {}, blunders {}, average loss {}",
+            player.name,
+            count(Judgement::Best),
+            count(Judgement::Inaccuracy),
+            count(Judgement::Mistake),
+            count(Judgement::Blunder),
+            total_loss / reviews.len().max(1) as i32
+        );
+        println!("{}", summary);
+        record.push(format!("# {}", summary));
+    }
+    record.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzle;
+
+    #[test]
+    fn judges_losses_at_the_thresholds() {
+        assert_eq!(Judgement::from_loss(0), Judgement::Best);
+        assert_eq!(Judgement::from_loss(INACCURACY_LOSS - 1), Judgement::Best);
+        assert_eq!(Judgement::from_loss(INACCURACY_LOSS), Judgement::Inaccuracy);
+        assert_eq!(
+            Judgement::from_loss(MISTAKE_LOSS - 1),
+            Judgement::Inaccuracy
+        );
+        assert_eq!(Judgement::from_loss(MISTAKE_LOSS), Judgement::Mistake);
+        assert_eq!(Judgement::from_loss(BLUNDER_LOSS - 1), Judgement::Mistake);
+        assert_eq!(Judgement::from_loss(BLUNDER_LOSS), Judgement::Blunder);
+    }
+
+    #[test]
+    fn summarises_a_short_game() {
+        let start: GameState = "x...../..X.X./o.x.o./..o.Xo/..o.../...... x XXX ooo"
+            .parse()
+            .unwrap();
+        let winning = puzzle::winning_moves(&start, 1)[0].clone();
+        let mut end = start.clone();
+        end.play(&winning).unwrap();
+        assert_eq!(
+            run(std::slice::from_ref(&start), &[(0, winning)], &end),
+            "# annotated boop game, ?! marks an inaccuracy, ? a mistake and ?? a blunder\n\
+             # start: x...../..X.X./o.x.o./..o.Xo/..o.../...... x XXX ooo\n\
+             1. Player 1: b,5,0 best (wins in 1)\n\
+             # result: Player 1 wins\n\
+             # Player 1: best 1, inaccuracies 0, mistakes 0, blunders 0, average loss 0\n\
+             # Player 2: best 0, inaccuracies 0, mistakes 0, blunders 0, average loss 0\n"
+        );
+
+        //A move that throws the win away is a blunder, one that only wins later can still be best
+        let review = start
+            .legal_moves()
+            .iter()
+            .filter_map(|player_move| review_move(&start, player_move))
+            .find(|review| review.played_score < ai::WIN_THRESHOLD)
+            .unwrap();
+        assert_eq!(review.best_score, ai::WIN_SCORE - 1);
+        assert_eq!(review.judgement(), Judgement::Blunder);
+        assert_eq!(run(&[], &[], &start), "");
+    }
+}
@@ -0,0 +1,286 @@
+//House rules read from the file passed with --rules, so variants don't need a rebuild. A file
+//ending in .json holds one JSON object, anything else is read as a flat subset of TOML with one
+//"key = value" per line and "#" comments. Tables and quoted keys are rejected:
+//
+//  width = 8
+//  height = 8
+//  line_length = 4
+//  cats_boop_cats = false
+//
+//Keys are the RuleSet fields, and any left out keep their value from the standard game
+use std::{error::Error, fmt};
+
+use crate::{
+    json::{Json, ParseJsonError},
+    InvalidRuleSetError, RuleSet,
+};
+
+#[derive(Debug)]
+pub struct ParseRulesError {
+    kind: RulesErrorKind,
+}
+#[derive(Debug)]
+enum RulesErrorKind {
+    InvalidLine(usize),
+    UnsupportedToml(usize),
+    InvalidJson(ParseJsonError),
+    NotAnObject,
+    UnknownKey(String),
+    DuplicateKey(String),
+    InvalidValue(String),
+    ImpossibleRules(InvalidRuleSetError),
+}
+impl ParseRulesError {
+    pub fn __description(&self) -> &str {
+        match self.kind {
+            RulesErrorKind::InvalidLine(_) => "line should be \"key = value\"",
+            RulesErrorKind::UnsupportedToml(_) => {
+                "only plain \"key = value\" lines are supported, not tables or quoted keys"
+            }
+            RulesErrorKind::InvalidJson(_) => "invalid json in rules file",
+            RulesErrorKind::NotAnObject => "json rules should be one object",
+            RulesErrorKind::UnknownKey(_) => "unknown rule",
+            RulesErrorKind::DuplicateKey(_) => "rule set more than once",
+            RulesErrorKind::InvalidValue(_) => "wrong kind of value for rule",
+            RulesErrorKind::ImpossibleRules(_) => "rules cannot be played",
+        }
+    }
+}
+//Names the line or rule at fault, which the description alone can't
+impl fmt::Display for ParseRulesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            RulesErrorKind::InvalidLine(line) | RulesErrorKind::UnsupportedToml(line) => {
+                write!(f, "{} on line {}", self.__description(), line)
+            }
+            RulesErrorKind::UnknownKey(key)
+            | RulesErrorKind::DuplicateKey(key)
+            | RulesErrorKind::InvalidValue(key) => {
+                write!(f, "{} \"{}\"", self.__description(), key)
+            }
+            _ => self.__description().fmt(f),
+        }
+    }
+}
+impl Error for ParseRulesError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            RulesErrorKind::InvalidJson(error) => Some(error),
+            RulesErrorKind::ImpossibleRules(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+//A value as written in either format, numbers keep their source text like Json does
+enum Value {
+    Number(String),
+    Bool(bool),
+    Other,
+}
+
+enum Field<'a> {
+    Number(&'a mut usize),
+    Bool(&'a mut bool),
+}
+
+pub fn from_toml(text: &str) -> Result<RuleSet, ParseRulesError> {
+    let mut values = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line.starts_with('[') || line.starts_with(['"', '\'']) {
+            return Err(ParseRulesError {
+                kind: RulesErrorKind::UnsupportedToml(index + 1),
+            });
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(ParseRulesError {
+                kind: RulesErrorKind::InvalidLine(index + 1),
+            });
+        };
+        let value = match value.trim() {
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            number if number.starts_with(|c: char| c.is_ascii_digit()) => {
+                Value::Number(number.to_string())
+            }
+            _ => Value::Other,
+        };
+        values.push((key.trim().to_string(), value));
+    }
+    build(values)
+}
+
+pub fn from_json(text: &str) -> Result<RuleSet, ParseRulesError> {
+    let json = text.parse::<Json>().map_err(|error| ParseRulesError {
+        kind: RulesErrorKind::InvalidJson(error),
+    })?;
+    let Json::Object(fields) = json else {
+        return Err(ParseRulesError {
+            kind: RulesErrorKind::NotAnObject,
+        });
+    };
+    let values = fields
+        .into_iter()
+        .map(|(key, value)| {
+            let value = match value {
+                Json::Number(number) => Value::Number(number),
+                Json::Bool(value) => Value::Bool(value),
+                _ => Value::Other,
+            };
+            (key, value)
+        })
+        .collect();
+    build(values)
+}
+
+//Sets every rule over the standard game's and checks the result can be played
+fn build(values: Vec<(String, Value)>) -> Result<RuleSet, ParseRulesError> {
+    let mut rules = RuleSet::default();
+    let mut seen: Vec<&str> = Vec::new();
+    for (key, value) in &values {
+        if seen.contains(&key.as_str()) {
+            return Err(ParseRulesError {
+                kind: RulesErrorKind::DuplicateKey(key.clone()),
+            });
+        }
+        seen.push(key);
+
+        let field = match key.as_str() {
+            "players" => Field::Number(&mut rules.players),
+            "width" => Field::Number(&mut rules.width),
+            "height" => Field::Number(&mut rules.height),
+            "pieces_per_player" => Field::Number(&mut rules.pieces_per_player),
+            "starting_cats" => Field::Number(&mut rules.starting_cats),
+            "line_length" => Field::Number(&mut rules.line_length),
+            "cat_cap" => Field::Number(&mut rules.cat_cap),
+            "toroidal" => Field::Bool(&mut rules.toroidal),
+            "cats_boop_cats" => Field::Bool(&mut rules.cats_boop_cats),
+            "booped_off_to_pool" => Field::Bool(&mut rules.booped_off_to_pool),
+            "cat_line_wins" => Field::Bool(&mut rules.cat_line_wins),
+            "all_cats_on_board_wins" => Field::Bool(&mut rules.all_cats_on_board_wins),
+            "swap_rule" => Field::Bool(&mut rules.swap_rule),
+            _ => {
+                return Err(ParseRulesError {
+                    kind: RulesErrorKind::UnknownKey(key.clone()),
+                })
+            }
+        };
+        let invalid_value = || ParseRulesError {
+            kind: RulesErrorKind::InvalidValue(key.clone()),
+        };
+        match (field, value) {
+            (Field::Number(field), Value::Number(number)) => {
+                *field = number.parse().map_err(|_| invalid_value())?
+            }
+            (Field::Bool(field), Value::Bool(value)) => *field = *value,
+            _ => return Err(invalid_value()),
+        }
+    }
+
+    rules.check().map_err(|error| ParseRulesError {
+        kind: RulesErrorKind::ImpossibleRules(error),
+    })?;
+    Ok(rules)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RuleSetErrorKind;
+
+    fn toml_error(text: &str) -> RulesErrorKind {
+        from_toml(text).unwrap_err().kind
+    }
+
+    #[test]
+    fn reads_toml_and_json() {
+        let text = "# eight by eight\nwidth = 8\nheight = 8 # square\n\nline_length = 4\ncats_boop_cats = false\n";
+        let rules = from_toml(text).unwrap();
+        assert_eq!((rules.width, rules.height, rules.line_length), (8, 8, 4));
+        assert!(!rules.cats_boop_cats);
+        assert_eq!(
+            rules.pieces_per_player,
+            RuleSet::default().pieces_per_player
+        );
+
+        let json = r#"{"width":8,"height":8,"line_length":4,"cats_boop_cats":false}"#;
+        assert_eq!(from_json(json).unwrap(), rules);
+    }
+
+    #[test]
+    fn rejects_unknown_key() {
+        assert!(
+            matches!(toml_error("colour = 3"), RulesErrorKind::UnknownKey(key) if key == "colour")
+        );
+        let error = from_json(r#"{"colour":3}"#).unwrap_err();
+        assert_eq!(error.to_string(), "unknown rule \"colour\"");
+    }
+
+    #[test]
+    fn rejects_duplicate_key() {
+        let error = from_toml("width = 7\nwidth = 8").unwrap_err();
+        assert_eq!(error.to_string(), "rule set more than once \"width\"");
+        assert!(matches!(
+            from_json(r#"{"width":7,"width":8}"#).unwrap_err().kind,
+            RulesErrorKind::DuplicateKey(_)
+        ));
+    }
+
+    #[test]
+    fn rejects_wrong_value_type() {
+        assert!(matches!(
+            toml_error("width = true"),
+            RulesErrorKind::InvalidValue(_)
+        ));
+        assert!(matches!(
+            toml_error("toroidal = 1"),
+            RulesErrorKind::InvalidValue(_)
+        ));
+        assert!(matches!(
+            toml_error("width = \"8\""),
+            RulesErrorKind::InvalidValue(_)
+        ));
+        assert!(matches!(
+            toml_error("width = 8.5"),
+            RulesErrorKind::InvalidValue(_)
+        ));
+        assert!(matches!(
+            from_json(r#"{"width":"8"}"#).unwrap_err().kind,
+            RulesErrorKind::InvalidValue(_)
+        ));
+    }
+
+    #[test]
+    fn rejects_impossible_combination() {
+        let error = from_toml("width = 3\nheight = 3\nline_length = 4").unwrap_err();
+        assert!(matches!(
+            &error.kind,
+            RulesErrorKind::ImpossibleRules(error) if error.kind == RuleSetErrorKind::LineTooLong
+        ));
+        assert!(matches!(
+            toml_error("cat_line_wins = false"),
+            RulesErrorKind::ImpossibleRules(_)
+        ));
+    }
+
+    #[test]
+    fn rejects_toml_beyond_key_value_lines() {
+        let error = from_toml("width = 8\n[board]\nheight = 8").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "only plain \"key = value\" lines are supported, not tables or quoted keys on line 2"
+        );
+        assert!(matches!(
+            toml_error("\"width\" = 8"),
+            RulesErrorKind::UnsupportedToml(1)
+        ));
+        assert!(matches!(
+            toml_error("width 8"),
+            RulesErrorKind::InvalidLine(1)
+        ));
+    }
+}
@@ -0,0 +1,676 @@
+//Small HTTP/JSON server that hosts any number of games at once. Every connection gets its own
+//thread and all games live behind one lock, so requests never see a half played move.
+//
+//  POST /games                 create a game, answers its id and state
+//  GET  /games                 ids of every game
+//  GET  /games/{id}            state of a game
+//  GET  /games/{id}/moves      legal moves for the player to move
+//  POST /games/{id}/moves      play a move sent as {"size":"small","x":2,"y":3}
+//  GET  /games/{id}/history    every move played so far with its events
+//  GET  /games/{id}/watch      websocket for spectators, a snapshot of the game then every event
+use std::{
+    collections::BTreeMap,
+    io::{self, BufRead, BufReader, Write},
+    net::Shutdown,
+    net::{TcpListener, TcpStream},
+    sync::{
+        mpsc::{self, Sender},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use crate::{
+    json::{self, Json},
+    websocket::{self, Frame},
+    GameState, Move, MoveEvent, RuleSet,
+};
+
+const MAX_BODY_LENGTH: usize = 64 * 1024;
+
+struct HistoryEntry {
+    player: usize,
+    player_move: Move,
+    events: Vec<MoveEvent>,
+}
+
+struct ServerGame {
+    game_state: GameState,
+    history: Vec<HistoryEntry>,
+    //one per watching websocket, dropped once the spectator has gone
+    spectators: Vec<Sender<Frame>>,
+}
+
+#[derive(Default)]
+struct Games {
+    rules: RuleSet,
+    next_id: u64,
+    games: BTreeMap<u64, ServerGame>,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn segments(&self) -> Vec<&str> {
+        self.path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .collect()
+    }
+}
+
+struct Response {
+    status: u16,
+    body: Json,
+}
+impl Response {
+    fn ok(body: Json) -> Response {
+        Response { status: 200, body }
+    }
+
+    fn error(status: u16, kind: &str, message: &str) -> Response {
+        Response {
+            status,
+            body: json::error_reply(json::error_json("HttpError", kind, message, None)),
+        }
+    }
+
+    fn reason(&self) -> &str {
+        match self.status {
+            200 => "OK",
+            201 => "Created",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            413 => "Payload Too Large",
+            422 => "Unprocessable Entity",
+            _ => "Error",
+        }
+    }
+}
+
+//Every game is played under the rules picked on the command line, the standard game by default
+pub fn run(listener: TcpListener, rules: RuleSet) -> io::Result<()> {
+    let games = Arc::new(Mutex::new(Games {
+        rules,
+        ..Games::default()
+    }));
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let games = Arc::clone(&games);
+        thread::spawn(move || {
+            //a client that hangs up halfway only loses its own response
+            let _ = handle_connection(stream, &games);
+        });
+    }
+    Ok(())
+}
+
+fn handle_connection(stream: TcpStream, games: &Mutex<Games>) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let response = match read_request(&mut reader)? {
+        Ok(request) => match (request.method.as_str(), request.segments().as_slice()) {
+            ("GET", ["games", id, "watch"]) => {
+                let id = id.to_string();
+                return watch(stream, reader, &request, &id, games);
+            }
+            _ => route(&request, games),
+        },
+        Err(response) => response,
+    };
+    write_response(stream, &response)
+}
+
+fn write_response(mut stream: TcpStream, response: &Response) -> io::Result<()> {
+    let body = response.body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        response.reason(),
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+//The outer error is the connection failing, the inner one a request we answer with an error
+fn read_request(reader: &mut impl BufRead) -> io::Result<Result<Request, Response>> {
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Ok(Err(Response::error(
+            400,
+            "BadRequest",
+            "malformed request line",
+        )));
+    };
+    let path = target.split('?').next().unwrap_or(target).to_string();
+    let method = method.to_string();
+
+    let mut headers = Vec::new();
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            break;
+        }
+        let header = header.trim();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                match value.trim().parse() {
+                    Ok(length) => content_length = length,
+                    Err(_) => {
+                        return Ok(Err(Response::error(
+                            400,
+                            "BadRequest",
+                            "invalid Content-Length",
+                        )))
+                    }
+                }
+            }
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    if content_length > MAX_BODY_LENGTH {
+        return Ok(Err(Response::error(
+            413,
+            "PayloadTooLarge",
+            "request body is too large",
+        )));
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    let Ok(body) = String::from_utf8(body) else {
+        return Ok(Err(Response::error(
+            400,
+            "BadRequest",
+            "request body is not utf-8",
+        )));
+    };
+
+    Ok(Ok(Request {
+        method,
+        path,
+        headers,
+        body,
+    }))
+}
+
+fn game_json(id: u64, game: &ServerGame) -> Json {
+    match json::state_json(&game.game_state) {
+        Json::Object(mut fields) => {
+            fields.insert(0, (String::from("id"), Json::Number(id.to_string())));
+            Json::Object(fields)
+        }
+        state => state,
+    }
+}
+
+fn history_json(game: &ServerGame) -> Json {
+    Json::Array(
+        game.history
+            .iter()
+            .map(|entry| {
+                Json::object(vec![
+                    ("player", Json::number(entry.player)),
+                    ("move", json::move_json(&entry.player_move)),
+                    (
+                        "events",
+                        Json::Array(entry.events.iter().map(json::event_json).collect()),
+                    ),
+                ])
+            })
+            .collect(),
+    )
+}
+
+fn route(request: &Request, games: &Mutex<Games>) -> Response {
+    let segments = request.segments();
+    //a panic in another request must not take every game down with it
+    let mut games = games
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    match (request.method.as_str(), segments.as_slice()) {
+        ("POST", ["games"]) => {
+            games.next_id += 1;
+            let id = games.next_id;
+            let game = ServerGame {
+                game_state: GameState::with_rules(games.rules),
+                history: Vec::new(),
+                spectators: Vec::new(),
+            };
+            let body = game_json(id, &game);
+            games.games.insert(id, game);
+            Response { status: 201, body }
+        }
+        ("GET", ["games"]) => Response::ok(Json::object(vec![(
+            "games",
+            Json::Array(
+                games
+                    .games
+                    .keys()
+                    .map(|id| Json::Number(id.to_string()))
+                    .collect(),
+            ),
+        )])),
+        (_, ["games"]) => Response::error(405, "MethodNotAllowed", "use GET or POST"),
+        (method, ["games", id, rest @ ..]) => {
+            let Some((id, game)) = id.parse::<u64>().ok().and_then(|id| {
+                let game = games.games.get_mut(&id)?;
+                Some((id, game))
+            }) else {
+                return Response::error(404, "NotFound", "no game with that id");
+            };
+            match (method, rest) {
+                ("GET", []) => Response::ok(game_json(id, game)),
+                ("GET", ["moves"]) => Response::ok(Json::object(vec![(
+                    "moves",
+                    Json::Array(
+                        game.game_state
+                            .legal_moves()
+                            .iter()
+                            .map(json::move_json)
+                            .collect(),
+                    ),
+                )])),
+                ("POST", ["moves"]) => play(id, game, &request.body),
+                ("GET", ["history"]) => {
+                    Response::ok(Json::object(vec![("history", history_json(game))]))
+                }
+                (_, [] | ["moves"] | ["history"] | ["watch"]) => {
+                    Response::error(405, "MethodNotAllowed", "method not allowed here")
+                }
+                _ => Response::error(404, "NotFound", "unknown path"),
+            }
+        }
+        _ => Response::error(404, "NotFound", "unknown path"),
+    }
+}
+
+fn play(id: u64, game: &mut ServerGame, body: &str) -> Response {
+    let value = match body.parse::<Json>() {
+        Ok(value) => value,
+        Err(error) => {
+            return Response {
+                status: 400,
+                body: json::error_reply(json::json_error_json(&error)),
+            }
+        }
+    };
+    let player_move = match json::move_from_json(&value) {
+        Ok(player_move) => player_move,
+        Err(error) => {
+            return Response {
+                status: 400,
+                body: json::error_reply(json::placement_error_json(&error)),
+            }
+        }
+    };
+
+    let player = game.game_state.to_move();
+    match game.game_state.play(&player_move) {
+        Ok(events) => {
+            let messages: Vec<String> = events
+                .iter()
+                .map(|event| {
+                    Json::object(vec![
+                        ("type", Json::string("event")),
+                        ("id", Json::Number(id.to_string())),
+                        ("player", Json::number(player)),
+                        ("event", json::event_json(event)),
+                    ])
+                    .to_string()
+                })
+                .collect();
+            game.spectators.retain(|spectator| {
+                messages
+                    .iter()
+                    .all(|message| spectator.send(Frame::Text(message.clone())).is_ok())
+            });
+            game.history.push(HistoryEntry {
+                player,
+                player_move,
+                events,
+            });
+            Response::ok(game_json(id, game))
+        }
+        Err(error) => Response {
+            status: 422,
+            body: json::error_reply(json::place_error_json(&error)),
+        },
+    }
+}
+
+//Upgrades the connection to a websocket and streams the game to a spectator until either side
+//closes it. The snapshot is taken under the same lock that registers the spectator, so no event
+//can fall between the two
+fn watch(
+    mut stream: TcpStream,
+    mut reader: BufReader<TcpStream>,
+    request: &Request,
+    id: &str,
+    games: &Mutex<Games>,
+) -> io::Result<()> {
+    let upgrade = request
+        .header("upgrade")
+        .is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket"));
+    let (true, Some(key)) = (upgrade, request.header("sec-websocket-key")) else {
+        let response = Response::error(400, "BadRequest", "expected a websocket upgrade");
+        return write_response(stream, &response);
+    };
+
+    let (sender, receiver) = mpsc::channel();
+    let snapshot = {
+        let mut games = games
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let Some((id, game)) = id.parse::<u64>().ok().and_then(|id| {
+            let game = games.games.get_mut(&id)?;
+            Some((id, game))
+        }) else {
+            let response = Response::error(404, "NotFound", "no game with that id");
+            return write_response(stream, &response);
+        };
+        game.spectators.push(sender.clone());
+        Json::object(vec![
+            ("type", Json::string("snapshot")),
+            ("game", game_json(id, game)),
+            ("history", history_json(game)),
+        ])
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        websocket::accept_key(key)
+    )?;
+    websocket::write_frame(&mut stream, &Frame::Text(snapshot.to_string()))?;
+
+    //spectators only listen, anything they send is answered with an error
+    thread::spawn(move || loop {
+        let reply = match websocket::read_frame(&mut reader) {
+            Ok(Frame::Text(_) | Frame::Binary(_)) => Frame::Text(
+                json::error_reply(json::error_json(
+                    "HttpError",
+                    "Forbidden",
+                    "spectators cannot play moves",
+                    None,
+                ))
+                .to_string(),
+            ),
+            Ok(Frame::Ping(data)) => Frame::Pong(data),
+            Ok(Frame::Pong(_)) => continue,
+            Ok(Frame::Close) | Err(_) => {
+                let _ = sender.send(Frame::Close);
+                break;
+            }
+        };
+        if sender.send(reply).is_err() {
+            break;
+        }
+    });
+
+    for frame in receiver {
+        let closing = matches!(frame, Frame::Close);
+        if websocket::write_frame(&mut stream, &frame).is_err() || closing {
+            break;
+        }
+    }
+    stream.shutdown(Shutdown::Both)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{io::Read, net::SocketAddr};
+
+    fn start() -> SocketAddr {
+        start_with(RuleSet::default())
+    }
+
+    fn start_with(rules: RuleSet) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || run(listener, rules));
+        address
+    }
+
+    //Sends one request and returns the status and the parsed body
+    fn request(address: SocketAddr, method: &str, path: &str, body: &str) -> (u16, Json) {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            body.len(),
+            body
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+        (status, body.parse().unwrap())
+    }
+
+    fn field<'a>(json: &'a Json, key: &str) -> &'a Json {
+        json.get(key).unwrap()
+    }
+
+    fn array_length(json: &Json) -> usize {
+        match json {
+            Json::Array(values) => values.len(),
+            _ => panic!("not an array: {}", json),
+        }
+    }
+
+    #[test]
+    fn plays_a_game_over_http() {
+        let address = start();
+
+        let (status, created) = request(address, "POST", "/games", "");
+        assert_eq!(status, 201);
+        let id = field(&created, "id").to_string();
+        assert_eq!(field(&created, "to_move"), &Json::number(0));
+
+        let (status, listed) = request(address, "GET", "/games", "");
+        assert_eq!(status, 200);
+        assert!(array_length(field(&listed, "games")) >= 1);
+
+        let (status, game) = request(address, "GET", &format!("/games/{}", id), "");
+        assert_eq!(status, 200);
+        assert_eq!(game, created);
+
+        let (status, moves) = request(address, "GET", &format!("/games/{}/moves", id), "");
+        assert_eq!(status, 200);
+        assert_eq!(array_length(field(&moves, "moves")), 36);
+
+        let path = format!("/games/{}/moves", id);
+        let (status, played) = request(address, "POST", &path, r#"{"size":"small","x":2,"y":3}"#);
+        assert_eq!(status, 200);
+        assert_eq!(field(&played, "to_move"), &Json::number(1));
+        assert_eq!(
+            field(&played, "board"),
+            &"[\"......\",\"......\",\"......\",\"..x...\",\"......\",\"......\"]"
+                .parse()
+                .unwrap()
+        );
+
+        let (status, history) = request(address, "GET", &format!("/games/{}/history", id), "");
+        assert_eq!(status, 200);
+        let history = field(&history, "history");
+        assert_eq!(array_length(history), 1);
+        let Json::Array(entries) = history else {
+            unreachable!()
+        };
+        assert_eq!(field(&entries[0], "player"), &Json::number(0));
+        assert_eq!(
+            field(&entries[0], "events").to_string(),
+            r#"[{"event":"Placed","piece":{"owner":0,"size":"small"},"at":{"x":2,"y":3}}]"#
+        );
+    }
+
+    #[test]
+    fn streams_moves_to_spectators() {
+        let address = start();
+        let (_, created) = request(address, "POST", "/games", "");
+        let id = field(&created, "id").to_string();
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "GET /games/{}/watch HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n",
+            id
+        )
+        .unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut head = Vec::new();
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line.trim().is_empty() {
+                break;
+            }
+            head.push(line.trim().to_string());
+        }
+        assert_eq!(head[0], "HTTP/1.1 101 Switching Protocols");
+        assert!(head.contains(&String::from(
+            "Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        )));
+
+        let message = |reader: &mut BufReader<TcpStream>| -> Json {
+            let Frame::Text(text) = websocket::read_frame(reader).unwrap() else {
+                panic!("not a text frame");
+            };
+            text.parse().unwrap()
+        };
+        let snapshot = message(&mut reader);
+        assert_eq!(field(&snapshot, "type"), &Json::string("snapshot"));
+        assert_eq!(field(&snapshot, "game"), &created);
+
+        let path = format!("/games/{}/moves", id);
+        let (status, _) = request(address, "POST", &path, r#"{"size":"small","x":2,"y":3}"#);
+        assert_eq!(status, 200);
+        let event = message(&mut reader);
+        assert_eq!(field(&event, "type"), &Json::string("event"));
+        assert_eq!(field(&event, "player"), &Json::number(0));
+        assert_eq!(
+            field(&event, "event").to_string(),
+            r#"{"event":"Placed","piece":{"owner":0,"size":"small"},"at":{"x":2,"y":3}}"#
+        );
+    }
+
+    #[test]
+    fn passes_a_player_with_nothing_to_place() {
+        //Player 1 is left with one piece on a row of four and nothing to place after four moves
+        let address = start_with(RuleSet {
+            width: 4,
+            height: 1,
+            line_length: 2,
+            pieces_per_player: 2,
+            cat_cap: 2,
+            booped_off_to_pool: false,
+            ..RuleSet::default()
+        });
+        let (_, created) = request(address, "POST", "/games", "");
+        let path = format!("/games/{}/moves", field(&created, "id"));
+        let mut played = created;
+        for x in [0, 2, 3, 2] {
+            let body = format!("{{\"size\":\"small\",\"x\":{},\"y\":0}}", x);
+            let (status, reply) = request(address, "POST", &path, &body);
+            assert_eq!(status, 200);
+            played = reply;
+        }
+        assert_eq!(field(&played, "to_move"), &Json::number(1));
+        let Json::Array(events) = field(&played, "events") else {
+            panic!("no events");
+        };
+        assert_eq!(
+            events.last().unwrap().to_string(),
+            r#"{"event":"Passed","player":0}"#
+        );
+
+        let (status, _) = request(address, "POST", &path, r#"{"size":"big","x":2,"y":0}"#);
+        assert_eq!(status, 200);
+    }
+
+    #[test]
+    fn survives_deeply_nested_body() {
+        let address = start();
+        let (_, created) = request(address, "POST", "/games", "");
+        let moves = format!("/games/{}/moves", field(&created, "id"));
+
+        let (status, error) = request(address, "POST", &moves, &"[".repeat(60_000));
+        assert_eq!(status, 400);
+        assert_eq!(field(&error, "kind"), &Json::string("TooDeep"));
+
+        let (status, _) = request(address, "GET", "/games", "");
+        assert_eq!(status, 200);
+    }
+
+    #[test]
+    fn answers_bad_requests_with_errors() {
+        let address = start();
+        let (_, created) = request(address, "POST", "/games", "");
+        let moves = format!("/games/{}/moves", field(&created, "id"));
+
+        let (status, error) = request(address, "POST", &moves, "{\"size\":");
+        assert_eq!(status, 400);
+        assert_eq!(field(&error, "error"), &Json::string("ParseJsonError"));
+
+        let (status, error) = request(address, "POST", &moves, r#"{"size":"huge","x":0,"y":0}"#);
+        assert_eq!(status, 400);
+        assert_eq!(field(&error, "type"), &Json::string("error"));
+
+        let (status, error) = request(address, "GET", "/games/999999", "");
+        assert_eq!(status, 404);
+        assert_eq!(field(&error, "kind"), &Json::string("NotFound"));
+
+        let (status, _) = request(address, "GET", "/nowhere", "");
+        assert_eq!(status, 404);
+
+        let (status, error) = request(address, "DELETE", "/games", "");
+        assert_eq!(status, 405);
+        assert_eq!(field(&error, "kind"), &Json::string("MethodNotAllowed"));
+
+        let (status, _) = request(address, "PUT", &moves, "");
+        assert_eq!(status, 405);
+
+        let (status, error) = request(address, "POST", &moves, r#"{"size":"small","x":9,"y":0}"#);
+        assert_eq!(status, 422);
+        assert_eq!(field(&error, "kind"), &Json::string("OutOfBoundsX"));
+
+        let placement = r#"{"size":"small","x":0,"y":0}"#;
+        request(address, "POST", &moves, placement);
+        let (status, error) = request(address, "POST", &moves, placement);
+        assert_eq!(status, 422);
+        assert_eq!(field(&error, "kind"), &Json::string("PositionOccupied"));
+    }
+
+    #[test]
+    fn creates_games_under_the_rules_given() {
+        let address = start_with(RuleSet::for_players(3));
+        let (status, created) = request(address, "POST", "/games", "");
+        assert_eq!(status, 201);
+        assert_eq!(array_length(field(&created, "pools")), 3);
+    }
+}
@@ -0,0 +1,428 @@
+//Full-screen terminal interface: arrow keys move a cursor over the board and Enter places a piece
+use std::{
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEventKind},
+    execute, queue,
+    style::Print,
+    terminal::{self, ClearType},
+};
+
+use crate::{
+    paint, Cell, Coordinate, GameState, Line, Move, MoveEvent, Piece, PieceErrorKind,
+    PiecePlacement, Preview, Size, PADDING, PLAYER_COLORS,
+};
+
+const PANEL_COLUMN: u16 = 20;
+const HISTORY_LINES: usize = 10;
+const FRAME_LENGTH: Duration = Duration::from_millis(400);
+
+enum Mode {
+    Placing,
+    //the board shows the placement, before is the position the move is played from
+    ChoosingLine {
+        before: Box<GameState>,
+        placement: PiecePlacement,
+        lines: Vec<Line>,
+    },
+    GameOver,
+}
+
+struct Tui {
+    game_state: GameState,
+    cursor: Coordinate,
+    size: Size,
+    history: Vec<String>,
+    status: String,
+    mode: Mode,
+    started: Instant,
+}
+
+pub fn run(game_state: GameState) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    terminal::enable_raw_mode()?;
+    execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+
+    let result = Tui::new(game_state).event_loop(&mut stdout);
+
+    execute!(stdout, cursor::Show, terminal::LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    result
+}
+
+fn size_name(size: Size) -> &'static str {
+    match size {
+        Size::Small => "kitten",
+        Size::Big => "cat",
+    }
+}
+
+//Arrow pointing from the placed piece towards a booped neighbour, screen y grows downwards. A
+//neighbour further away than one cell is across the edge of a toroidal board
+fn boop_arrow(placed: Coordinate, from: Coordinate) -> char {
+    let direction = |placed: usize, from: usize| match from as isize - placed as isize {
+        distance if distance > 1 => -1,
+        distance if distance < -1 => 1,
+        distance => distance,
+    };
+    match (direction(placed.x, from.x), direction(placed.y, from.y)) {
+        (-1, -1) => '↖',
+        (0, -1) => '↑',
+        (1, -1) => '↗',
+        (-1, 0) => '←',
+        (1, 0) => '→',
+        (-1, 1) => '↙',
+        (0, 1) => '↓',
+        _ => '↘',
+    }
+}
+
+impl Tui {
+    fn new(mut game_state: GameState) -> Tui {
+        //play passes for stuck players after every move, a starting position may need it too
+        let passes = game_state.pass_stuck_players();
+        let mut tui = Tui {
+            game_state,
+            cursor: Coordinate { x: 0, y: 0 },
+            size: Size::Small,
+            history: Vec::new(),
+            status: String::new(),
+            mode: Mode::Placing,
+            started: Instant::now(),
+        };
+        tui.record_passes(&passes);
+        tui
+    }
+
+    fn event_loop(&mut self, stdout: &mut io::Stdout) -> io::Result<()> {
+        loop {
+            self.draw(stdout)?;
+
+            if !event::poll(FRAME_LENGTH)? {
+                continue;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Left => self.cursor.x = self.cursor.x.saturating_sub(1),
+                KeyCode::Right => {
+                    self.cursor.x = (self.cursor.x + 1).min(self.game_state.rules.width - 1)
+                }
+                KeyCode::Up => self.cursor.y = self.cursor.y.saturating_sub(1),
+                KeyCode::Down => {
+                    self.cursor.y = (self.cursor.y + 1).min(self.game_state.rules.height - 1)
+                }
+                KeyCode::Char('s') => self.size = Size::Small,
+                KeyCode::Char('b') => self.size = Size::Big,
+                KeyCode::Enter => self.confirm(),
+                KeyCode::Char('w') => self.swap(),
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                _ => (),
+            }
+        }
+    }
+
+    //Cursor position in the padded coordinates used by the game board
+    fn board_cursor(&self) -> Coordinate {
+        self.cursor.padded()
+    }
+
+    fn player_to_move(&self) -> usize {
+        self.game_state.turn_count % self.game_state.turn_order.len()
+    }
+
+    fn confirm(&mut self) {
+        let placement = PiecePlacement {
+            coordinate: self.cursor,
+            size: self.size,
+        };
+        match &self.mode {
+            Mode::Placing => {
+                let player_move = Move::Place {
+                    placement: placement.clone(),
+                    line: None,
+                };
+                let mover = self.player_to_move();
+                let before = self.game_state.clone();
+                match self.game_state.play(&player_move) {
+                    Ok(events) => self.finish_turn(mover, &player_move, &events),
+                    Err(error) if error.kind == PieceErrorKind::MissingLineChoice => {
+                        //the board shows the piece and its boops while a line is picked, the
+                        //move itself is played once the line is known
+                        let _ = self.game_state.place_piece(placement.clone());
+                        let lines = self.game_state.lines();
+                        self.status = "Several lines: pick a piece to graduate".to_string();
+                        self.mode = Mode::ChoosingLine {
+                            before: Box::new(before),
+                            placement,
+                            lines,
+                        };
+                    }
+                    Err(error) => self.status = error.to_string(),
+                }
+            }
+            Mode::ChoosingLine {
+                before, placement, ..
+            } => {
+                let mut after = GameState::clone(before);
+                let player_move = Move::Place {
+                    placement: placement.clone(),
+                    line: Some(self.cursor),
+                };
+                let Ok(events) = after.play(&player_move) else {
+                    self.status = "That piece is not part of a line".to_string();
+                    return;
+                };
+                //the placing player is still the one to move in the shown board
+                let mover = self.player_to_move();
+                self.game_state = after;
+                self.finish_turn(mover, &player_move, &events);
+            }
+            Mode::GameOver => (),
+        }
+    }
+
+    //Takes over the opening piece under the swap rule
+    fn swap(&mut self) {
+        if !matches!(self.mode, Mode::Placing) {
+            return;
+        }
+        let player_name = self.game_state.turn_order[self.player_to_move()]
+            .name
+            .clone();
+        if let Err(error) = self.game_state.play(&Move::Swap) {
+            self.status = error.to_string();
+            return;
+        }
+        self.history
+            .push(format!("{}: {}", player_name, Move::Swap));
+        self.status.clear();
+    }
+
+    fn finish_turn(&mut self, mover: usize, player_move: &Move, events: &[MoveEvent]) {
+        self.history.push(format!(
+            "{}: {}",
+            self.game_state.turn_order[mover].name, player_move
+        ));
+        if let Some(winner) = self.game_state.winner {
+            self.status = format!("{} wins!", self.game_state.turn_order[winner].name);
+            self.mode = Mode::GameOver;
+            return;
+        }
+        self.status.clear();
+        self.mode = Mode::Placing;
+        self.record_passes(events);
+    }
+
+    //Lists the turns players with no pieces left to place sat out, and ends the game in a draw
+    //when nobody can move
+    fn record_passes(&mut self, events: &[MoveEvent]) {
+        for event in events {
+            if let MoveEvent::Passed { player } = event {
+                let player_name = &self.game_state.turn_order[*player].name;
+                self.history.push(format!("{}: pass", player_name));
+            }
+        }
+        if self.game_state.nobody_can_move() {
+            self.status = String::from("Nobody can move, the game is a draw");
+            self.mode = Mode::GameOver;
+        }
+    }
+
+    fn cell_text(
+        &self,
+        coordinate: Coordinate,
+        animation_frame: bool,
+        preview: Option<&Preview>,
+    ) -> String {
+        let cursor = self.board_cursor();
+        let cell = &self.game_state.game_board[coordinate.x][coordinate.y];
+        let mut codes = Vec::new();
+        let mut glyph = match cell {
+            Cell::Piece(piece) => {
+                codes.push(PLAYER_COLORS[piece.owner]);
+                if piece.size == Size::Big {
+                    codes.push("1");
+                }
+                piece.glyph()
+            }
+            _ => '_',
+        };
+
+        match &self.mode {
+            Mode::Placing => {
+                let Some(preview) = preview else {
+                    return Tui::styled(glyph, &codes, coordinate == cursor);
+                };
+                if coordinate == cursor {
+                    let ghost = Piece {
+                        owner: self.player_to_move(),
+                        size: self.size,
+                    };
+                    glyph = ghost.glyph();
+                    codes = vec![PLAYER_COLORS[ghost.owner], "2"];
+                }
+                for boop in &preview.boops {
+                    let from = boop.from.padded();
+                    if from == coordinate {
+                        codes.push("43");
+                        if animation_frame {
+                            glyph = boop_arrow(cursor, from);
+                        }
+                    } else if boop.to.map(Coordinate::padded) == Some(coordinate) && animation_frame
+                    {
+                        if let Cell::Piece(piece) = &self.game_state.game_board[from.x][from.y] {
+                            glyph = piece.glyph();
+                            codes = vec![PLAYER_COLORS[piece.owner], "2"];
+                        }
+                    }
+                }
+            }
+            Mode::ChoosingLine { lines, .. }
+                if lines.iter().any(|line| line.contains(coordinate)) =>
+            {
+                codes.push("43");
+            }
+            _ => (),
+        }
+        let at_cursor = coordinate == cursor && !matches!(self.mode, Mode::GameOver);
+        Tui::styled(glyph, &codes, at_cursor)
+    }
+
+    fn styled(glyph: char, codes: &[&str], at_cursor: bool) -> String {
+        let mut codes = codes.to_vec();
+        if at_cursor {
+            codes.push("7");
+        }
+        if codes.is_empty() {
+            glyph.to_string()
+        } else {
+            paint(&glyph.to_string(), &codes)
+        }
+    }
+
+    //What placing the selected piece under the cursor would do, if it is a legal move
+    fn preview(&self) -> Option<Preview> {
+        match self.mode {
+            Mode::Placing => self
+                .game_state
+                .preview(PiecePlacement {
+                    coordinate: self.cursor,
+                    size: self.size,
+                })
+                .ok(),
+            _ => None,
+        }
+    }
+
+    fn board_lines(&self) -> Vec<String> {
+        let animation_frame =
+            (self.started.elapsed().as_millis() / FRAME_LENGTH.as_millis()) % 2 == 1;
+        let preview = self.preview();
+        let rules = &self.game_state.rules;
+        let column_width = self.column_width();
+        let mut header = String::from("y\\x");
+        for x in 0..rules.width {
+            header += &format!(" {:>column_width$}", x);
+        }
+        let mut lines = vec![header];
+        for y in PADDING..PADDING + rules.height {
+            let mut line = format!("{:>3}", y - PADDING);
+            for x in PADDING..PADDING + rules.width {
+                line += &" ".repeat(column_width);
+                line += &self.cell_text(Coordinate { x, y }, animation_frame, preview.as_ref());
+            }
+            lines.push(line);
+        }
+        lines
+    }
+
+    //Columns are as wide as the widest x label so they stay lined up past x=9
+    fn column_width(&self) -> usize {
+        self.game_state
+            .rules
+            .width
+            .saturating_sub(1)
+            .to_string()
+            .len()
+    }
+
+    fn panel_lines(&self) -> Vec<String> {
+        let to_move = self.player_to_move();
+        let mut lines = Vec::new();
+        for (player_index, player) in self.game_state.turn_order.iter().enumerate() {
+            let (kittens, cats) = player.pool_counts();
+            let glyph = Piece {
+                owner: player_index,
+                size: Size::Small,
+            }
+            .glyph();
+            let mut label = player.name.clone();
+            if player_index == to_move && !matches!(self.mode, Mode::GameOver) {
+                label = paint(&format!("> {}", label), &["1"]);
+            } else {
+                label = format!("  {}", label);
+            }
+            lines.push(format!(
+                "{} ({}) kittens: {}, cats: {}",
+                label,
+                paint(&glyph.to_string(), &[PLAYER_COLORS[player_index]]),
+                kittens,
+                cats
+            ));
+        }
+        lines.push(String::new());
+        lines.push(format!("Placing: {} (s/b to change)", size_name(self.size)));
+        if self.game_state.can_swap() {
+            lines.push(String::from("w: swap and take the opening piece"));
+        }
+        if let Some(Preview {
+            winner: Some(winner),
+            ..
+        }) = self.preview()
+        {
+            lines.push(format!(
+                "This move wins for {}",
+                self.game_state.turn_order[winner].name
+            ));
+        }
+        lines.push(String::new());
+        lines.push(String::from("History:"));
+        let skipped = self.history.len().saturating_sub(HISTORY_LINES);
+        for (index, entry) in self.history.iter().enumerate().skip(skipped) {
+            lines.push(format!("{:>3}. {}", index + 1, entry));
+        }
+        lines.push(String::new());
+        lines.push(self.status.clone());
+        lines.push(String::from(
+            "arrows: move  s/b: size  enter: place  w: swap  q: quit",
+        ));
+        lines
+    }
+
+    fn draw(&self, stdout: &mut io::Stdout) -> io::Result<()> {
+        queue!(stdout, terminal::Clear(ClearType::All))?;
+        for (row, line) in self.board_lines().iter().enumerate() {
+            queue!(stdout, cursor::MoveTo(0, row as u16), Print(line))?;
+        }
+        //the side panel moves right to make room for wide boards
+        let board_width = 3 + self.game_state.rules.width * (self.column_width() + 1);
+        let panel_column = PANEL_COLUMN.max(board_width as u16 + 2);
+        for (row, line) in self.panel_lines().iter().enumerate() {
+            queue!(
+                stdout,
+                cursor::MoveTo(panel_column, row as u16),
+                Print(line)
+            )?;
+        }
+        stdout.flush()
+    }
+}